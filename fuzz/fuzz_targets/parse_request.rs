@@ -0,0 +1,18 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use http_types::{ParserProfile, RequestParser};
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&selector, input)) = data.split_first() else {
+        return;
+    };
+
+    let profile = match selector % 3 {
+        0 => ParserProfile::Strict,
+        1 => ParserProfile::Standard,
+        _ => ParserProfile::Lenient,
+    };
+
+    let _ = RequestParser::new(profile).parse_request(input);
+});
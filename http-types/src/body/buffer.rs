@@ -0,0 +1,127 @@
+use std::{io::ErrorKind, path::PathBuf, pin::Pin, sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex}};
+
+use tokio::{fs::OpenOptions, io::{AsyncRead, AsyncWriteExt}};
+
+use super::{Body, BodyRepr, SpillFile};
+use crate::memory::{MemoryAccount, Reservation};
+
+pub type BodyReader = Pin<Box<dyn AsyncRead + Send>>;
+
+const SPILL_DIR_ATTEMPTS: usize = 16;
+
+static SPILL_COUNTER: AtomicU64 = AtomicU64::new(0);
+static SPILL_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+impl SpillFile {
+    async fn create() -> std::io::Result<(tokio::fs::File, Self)> {
+        let id = SPILL_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let path = spill_dir()?.join(format!("body-{}", id));
+
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+
+        let file = options.open(&path).await?;
+        Ok((file, Self { path }))
+    }
+}
+
+// Spilled bodies go in a directory only this process can enter, with a random name so
+// other local users can neither read uploads nor plant files or symlinks ahead of time.
+fn spill_dir() -> std::io::Result<PathBuf> {
+    let mut dir = SPILL_DIR.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(dir) = dir.as_ref() {
+        return Ok(dir.clone());
+    }
+
+    let mut builder = std::fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+
+    for _ in 0..SPILL_DIR_ATTEMPTS {
+        let suffix = getrandom::u64().map_err(std::io::Error::from)?;
+        let path = std::env::temp_dir().join(format!("rust-http-server-{}-{:016x}", std::process::id(), suffix));
+        match builder.create(&path) {
+            Ok(()) => {
+                *dir = Some(path.clone());
+                return Ok(path);
+            },
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Err(std::io::Error::new(ErrorKind::AlreadyExists, "Failed to create a directory for spilled bodies"))
+}
+
+impl Body {
+    pub async fn bytes(&self) -> std::io::Result<Vec<u8>> {
+        match &self.repr {
+            BodyRepr::Memory(bytes) => Ok(bytes.clone()),
+            BodyRepr::File { file, .. } => tokio::fs::read(&file.path).await,
+        }
+    }
+
+    pub async fn reader(&self) -> std::io::Result<BodyReader> {
+        match &self.repr {
+            BodyRepr::Memory(bytes) => Ok(Box::pin(std::io::Cursor::new(bytes.clone()))),
+            BodyRepr::File { file, .. } => Ok(Box::pin(tokio::fs::File::open(&file.path).await?)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct BodyBuffer {
+    threshold: usize,
+    memory: Vec<u8>,
+    reservation: Option<Reservation>,
+    spilled: Option<(tokio::fs::File, SpillFile)>,
+    len: u64,
+}
+
+impl BodyBuffer {
+    pub fn new(threshold: usize) -> Self {
+        Self { threshold, memory: Vec::new(), reservation: None, spilled: None, len: 0 }
+    }
+
+    pub fn with_account(mut self, account: &Arc<MemoryAccount>) -> Self {
+        self.reservation = Some(account.reserve());
+        self
+    }
+
+    pub async fn write(&mut self, chunk: &[u8]) -> std::io::Result<()> {
+        self.len += chunk.len() as u64;
+
+        if let Some((file, _)) = &mut self.spilled {
+            return file.write_all(chunk).await;
+        }
+
+        let fits = self.memory.len() + chunk.len() <= self.threshold
+            && self.reservation.as_mut().is_none_or(|reservation| reservation.grow(chunk.len()));
+
+        if fits {
+            self.memory.extend_from_slice(chunk);
+            return Ok(());
+        }
+
+        let (mut file, spill) = SpillFile::create().await?;
+        file.write_all(&self.memory).await?;
+        file.write_all(chunk).await?;
+
+        self.memory = Vec::new();
+        self.reservation = None;
+        self.spilled = Some((file, spill));
+        Ok(())
+    }
+
+    pub async fn finish(self) -> std::io::Result<Body> {
+        match self.spilled {
+            None => Ok(Body { repr: BodyRepr::Memory(self.memory), _reservation: self.reservation.map(Arc::new) }),
+            Some((mut file, spill)) => {
+                file.flush().await?;
+                Ok(Body { repr: BodyRepr::File { file: Arc::new(spill), len: self.len }, _reservation: None })
+            },
+        }
+    }
+}
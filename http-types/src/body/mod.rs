@@ -0,0 +1,130 @@
+use std::{path::PathBuf, sync::Arc};
+
+use serde::{de::{SeqAccess, Visitor}, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::memory::Reservation;
+
+#[cfg(feature = "tokio")]
+mod buffer;
+
+#[cfg(feature = "tokio")]
+pub use buffer::*;
+
+#[derive(Debug)]
+struct SpillFile {
+    path: PathBuf,
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            log::warn!("Failed to remove spilled body {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+enum BodyRepr {
+    Memory(Vec<u8>),
+    File { file: Arc<SpillFile>, len: u64 },
+}
+
+#[derive(Debug, Clone)]
+pub struct Body {
+    repr: BodyRepr,
+    _reservation: Option<Arc<Reservation>>,
+}
+
+impl Body {
+    pub fn empty() -> Self {
+        Self::from(Vec::new())
+    }
+
+    pub fn len(&self) -> u64 {
+        match &self.repr {
+            BodyRepr::Memory(bytes) => bytes.len() as u64,
+            BodyRepr::File { len, .. } => *len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_spilled(&self) -> bool {
+        matches!(self.repr, BodyRepr::File { .. })
+    }
+
+    pub fn in_memory(&self) -> Option<&[u8]> {
+        match &self.repr {
+            BodyRepr::Memory(bytes) => Some(bytes),
+            BodyRepr::File { .. } => None,
+        }
+    }
+}
+
+impl Default for Body {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl From<Vec<u8>> for Body {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self { repr: BodyRepr::Memory(bytes), _reservation: None }
+    }
+}
+
+impl Serialize for Body {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let bytes = self.in_memory()
+            .ok_or_else(|| serde::ser::Error::custom("a body spilled to disk cannot be serialized"))?;
+
+        serialize_bytes(bytes, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Body {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_bytes(deserializer).map(Body::from)
+    }
+}
+
+pub(crate) fn serialize_bytes<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => serializer.serialize_str(text),
+        Err(_) => serializer.serialize_bytes(bytes),
+    }
+}
+
+pub(crate) fn deserialize_bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    struct BytesVisitor;
+
+    impl<'de> Visitor<'de> for BytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "a string or a sequence of bytes")
+        }
+
+        fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+            Ok(v.as_bytes().to_vec())
+        }
+
+        fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+            Ok(v.to_vec())
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+            let mut bytes = Vec::with_capacity(access.size_hint().unwrap_or(0));
+            while let Some(b) = access.next_element::<u8>()? {
+                bytes.push(b);
+            }
+
+            Ok(bytes)
+        }
+    }
+
+    deserializer.deserialize_any(BytesVisitor)
+}
@@ -0,0 +1,136 @@
+use std::fmt::Display;
+
+use serde::{de::{MapAccess, Visitor}, ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::ParseRequestErr;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeaderMap {
+    entries: Vec<(String, String)>,
+}
+
+impl HeaderMap {
+    pub fn new() -> Self {
+        Self { entries: Vec::new() }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.entries
+            .iter()
+            .filter(move |(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn get_combined(&self, key: &str) -> Option<String> {
+        let values = self.get_all(key).collect::<Vec<_>>();
+        (!values.is_empty()).then(|| values.join(", "))
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn insert(&mut self, key: impl Display, val: impl Display) -> Option<String> {
+        let key = sanitize(key);
+        let val = sanitize(val);
+
+        let Some(index) = self.entries.iter().position(|(k, _)| k.eq_ignore_ascii_case(&key)) else {
+            self.entries.push((key, val));
+            return None;
+        };
+
+        let mut rest = self.entries.split_off(index + 1);
+        rest.retain(|(k, _)| !k.eq_ignore_ascii_case(&key));
+        let (_, previous) = std::mem::replace(&mut self.entries[index], (key, val));
+        self.entries.append(&mut rest);
+        Some(previous)
+    }
+
+    pub fn append(&mut self, key: impl Display, val: impl Display) {
+        self.entries.push((sanitize(key), sanitize(val)));
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let index = self.entries.iter().position(|(k, _)| k.eq_ignore_ascii_case(key))?;
+        let (_, removed) = self.entries.remove(index);
+        self.entries.retain(|(k, _)| !k.eq_ignore_ascii_case(key));
+        Some(removed)
+    }
+
+    pub fn content_length(&self) -> Option<Result<u64, ParseRequestErr>> {
+        let combined = self.get_combined("Content-Length")?;
+        let invalid = || ParseRequestErr::InvalidHeader(format!("Content-Length: {}", combined));
+        let lengths = combined.split(',')
+            .map(|length| length.trim().parse::<u64>().map_err(|_| invalid()))
+            .collect::<Result<Vec<_>, _>>();
+
+        Some(lengths.and_then(|lengths| match lengths.windows(2).all(|pair| pair[0] == pair[1]) {
+            true => Ok(lengths[0]),
+            false => Err(invalid()),
+        }))
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.get("Content-Type")
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+// CR, LF and NUL are never valid in a header, and CR or LF would let a value inject headers or a body.
+fn sanitize(text: impl Display) -> String {
+    text.to_string().replace(['\r', '\n', '\0'], " ")
+}
+
+impl Serialize for HeaderMap {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (key, val) in self.iter() {
+            map.serialize_entry(key, val)?;
+        }
+
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for HeaderMap {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct HeaderMapVisitor;
+
+        impl<'de> Visitor<'de> for HeaderMapVisitor {
+            type Value = HeaderMap;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "a map of header names to values")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+                let mut headers = HeaderMap::new();
+                while let Some((key, val)) = access.next_entry::<String, String>()? {
+                    headers.append(key, val);
+                }
+
+                Ok(headers)
+            }
+        }
+
+        deserializer.deserialize_map(HeaderMapVisitor)
+    }
+}
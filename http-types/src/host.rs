@@ -0,0 +1,101 @@
+use std::{fmt::Display, net::{Ipv4Addr, Ipv6Addr}, str::FromStr};
+
+use super::{ParseRequestErr, Result};
+
+const MAX_HOST_LEN: usize = 253;
+const MAX_LABEL_LEN: usize = 63;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HostName {
+    Domain(String),
+    Ipv4(Ipv4Addr),
+    Ipv6(Ipv6Addr),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Host {
+    name: HostName,
+    port: Option<u16>,
+}
+
+impl Host {
+    pub fn name(&self) -> &HostName {
+        &self.name
+    }
+
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
+
+    pub fn to_unicode(&self) -> String {
+        match &self.name {
+            HostName::Domain(domain) => idna::domain_to_unicode(domain).0,
+            HostName::Ipv4(addr) => addr.to_string(),
+            HostName::Ipv6(addr) => format!("[{}]", addr),
+        }
+    }
+}
+
+impl FromStr for Host {
+    type Err = ParseRequestErr;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || ParseRequestErr::InvalidHost(s.to_string());
+
+        let (name, port) = match s.strip_prefix('[') {
+            Some(rest) => {
+                let (addr, rest) = rest.split_once(']').ok_or_else(invalid)?;
+                let addr = HostName::Ipv6(addr.parse().map_err(|_| invalid())?);
+                match rest {
+                    "" => (addr, None),
+                    rest => (addr, Some(rest.strip_prefix(':').ok_or_else(invalid)?)),
+                }
+            },
+            None => {
+                let (name, port) = match s.rsplit_once(':') {
+                    Some((name, port)) => (name, Some(port)),
+                    None => (s, None),
+                };
+
+                let name = match name.parse() {
+                    Ok(addr) => HostName::Ipv4(addr),
+                    Err(_) => HostName::Domain(normalize_domain(name).ok_or_else(invalid)?),
+                };
+
+                (name, port)
+            },
+        };
+
+        let port = match port {
+            Some(port) => Some(port.parse().map_err(|_| invalid())?),
+            None => None,
+        };
+
+        Ok(Self { name, port })
+    }
+}
+
+impl Display for Host {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            HostName::Domain(domain) => write!(f, "{}", domain)?,
+            HostName::Ipv4(addr) => write!(f, "{}", addr)?,
+            HostName::Ipv6(addr) => write!(f, "[{}]", addr)?,
+        }
+
+        match self.port {
+            Some(port) => write!(f, ":{}", port),
+            None => Ok(()),
+        }
+    }
+}
+
+fn normalize_domain(name: &str) -> Option<String> {
+    let domain = idna::domain_to_ascii(name.strip_suffix('.').unwrap_or(name)).ok()?;
+    let valid = !domain.is_empty()
+        && domain.len() <= MAX_HOST_LEN
+        && domain.split('.').all(|label| !label.is_empty() && label.len() <= MAX_LABEL_LEN)
+        && domain.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'.');
+
+    valid.then_some(domain)
+}
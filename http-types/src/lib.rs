@@ -1,11 +1,32 @@
+#![allow(non_local_definitions)]
+
+pub mod memory;
+pub mod middleware;
+pub mod router;
+pub mod uri;
+
+mod body;
+mod extensions;
+mod headers;
+mod host;
+mod parser;
+mod raw;
 mod request;
 mod response;
 
+pub use body::*;
+pub use extensions::*;
+pub use headers::*;
+pub use host::*;
+pub use parser::*;
+pub use raw::*;
 pub use request::*;
 pub use response::*;
 
 use std::str::FromStr;
 
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct HttpVersion {
     major: u32,
@@ -50,4 +71,18 @@ impl std::fmt::Display for HttpVersion {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+impl Serialize for HttpVersion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for HttpVersion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
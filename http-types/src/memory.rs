@@ -0,0 +1,104 @@
+use std::sync::{atomic::{AtomicU64, AtomicUsize, Ordering}, Arc};
+
+#[derive(Debug)]
+pub struct MemoryAccount {
+    name: &'static str,
+    cap: Option<usize>,
+    used: AtomicUsize,
+    peak: AtomicUsize,
+    shed: AtomicU64,
+}
+
+impl MemoryAccount {
+    pub fn new(name: &'static str, cap: Option<usize>) -> Self {
+        Self { name, cap, used: AtomicUsize::new(0), peak: AtomicUsize::new(0), shed: AtomicU64::new(0) }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn cap(&self) -> Option<usize> {
+        self.cap
+    }
+
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    pub fn peak(&self) -> usize {
+        self.peak.load(Ordering::Relaxed)
+    }
+
+    pub fn shed(&self) -> u64 {
+        self.shed.load(Ordering::Relaxed)
+    }
+
+    pub fn reserve(self: &Arc<Self>) -> Reservation {
+        Reservation { account: self.clone(), bytes: 0 }
+    }
+
+    fn try_add(&self, bytes: usize) -> bool {
+        let result = self.used.fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+            let total = used.checked_add(bytes)?;
+            match self.cap {
+                Some(cap) if total > cap => None,
+                _ => Some(total),
+            }
+        });
+
+        match result {
+            Ok(used) => {
+                self.peak.fetch_max(used + bytes, Ordering::Relaxed);
+                true
+            },
+            Err(_) => {
+                self.shed.fetch_add(1, Ordering::Relaxed);
+                false
+            },
+        }
+    }
+
+    fn release(&self, bytes: usize) {
+        self.used.fetch_sub(bytes, Ordering::AcqRel);
+    }
+}
+
+impl std::fmt::Display for MemoryAccount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} bytes (peak: {}, cap: ", self.name, self.used(), self.peak())?;
+        match self.cap {
+            Some(cap) => write!(f, "{}", cap)?,
+            None => write!(f, "none")?,
+        }
+
+        write!(f, ", shed: {})", self.shed())
+    }
+}
+
+#[derive(Debug)]
+pub struct Reservation {
+    account: Arc<MemoryAccount>,
+    bytes: usize,
+}
+
+impl Reservation {
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    pub fn grow(&mut self, bytes: usize) -> bool {
+        if !self.account.try_add(bytes) {
+            return false;
+        }
+
+        self.bytes += bytes;
+        true
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        self.account.release(self.bytes);
+    }
+}
@@ -0,0 +1,67 @@
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use crate::{router::HandlerFn, HttpRequest, HttpResponse};
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+pub trait Middleware: Send + Sync + 'static {
+    fn handle(&self, request: &mut HttpRequest, next: Next<'_>) -> impl Future<Output = HttpResponse> + Send;
+
+    // Runs before the body is read for requests that send Expect: 100-continue.
+    fn check_head(&self, _request: &mut HttpRequest) -> Option<HttpResponse> {
+        None
+    }
+}
+
+pub(crate) trait DynMiddleware: Send + Sync {
+    fn handle_boxed<'a>(&'a self, request: &'a mut HttpRequest, next: Next<'a>) -> BoxFuture<'a, HttpResponse>;
+    fn check_head(&self, request: &mut HttpRequest) -> Option<HttpResponse>;
+}
+
+impl<T: Middleware> DynMiddleware for T {
+    fn handle_boxed<'a>(&'a self, request: &'a mut HttpRequest, next: Next<'a>) -> BoxFuture<'a, HttpResponse> {
+        Box::pin(self.handle(request, next))
+    }
+
+    fn check_head(&self, request: &mut HttpRequest) -> Option<HttpResponse> {
+        Middleware::check_head(self, request)
+    }
+}
+
+pub(crate) type MiddlewareFn = Arc<dyn DynMiddleware>;
+
+pub struct Next<'a> {
+    global: &'a [MiddlewareFn],
+    route: &'a [MiddlewareFn],
+    endpoint: Option<&'a HandlerFn>,
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(global: &'a [MiddlewareFn], route: &'a [MiddlewareFn], endpoint: Option<&'a HandlerFn>) -> Self {
+        Self { global, route, endpoint }
+    }
+
+    pub async fn run(self, request: &mut HttpRequest) -> HttpResponse {
+        if let Some((middleware, global)) = self.global.split_first() {
+            return middleware.handle_boxed(request, Next { global, ..self }).await;
+        }
+
+        if let Some((middleware, route)) = self.route.split_first() {
+            return middleware.handle_boxed(request, Next { route, ..self }).await;
+        }
+
+        match self.endpoint {
+            Some(endpoint) => endpoint(request),
+            None => HttpResponse::not_found(),
+        }
+    }
+}
+
+impl std::fmt::Debug for Next<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Next")
+            .field("remaining", &(self.global.len() + self.route.len()))
+            .field("endpoint", &self.endpoint.is_some())
+            .finish()
+    }
+}
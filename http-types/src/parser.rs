@@ -0,0 +1,344 @@
+use std::str::FromStr;
+
+use super::{Body, EncodedPathPolicy, HeadParser, HeaderMap, HttpMethod, HttpRequest, HttpResponse, HttpStatusCode, HttpVersion, ParseRequestErr, RawError, RawHeader, Result, Route, Status};
+
+const MAX_HEADERS: usize = 128;
+const CHUNK_ERROR_CONTEXT: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ParserProfile {
+    Strict,
+    #[default]
+    Standard,
+    Lenient,
+}
+
+impl FromStr for ParserProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "strict" => Ok(Self::Strict),
+            "standard" => Ok(Self::Standard),
+            "lenient" => Ok(Self::Lenient),
+            _ => Err(format!("'{}' is not a valid parser profile", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct RequestParser {
+    profile: ParserProfile,
+    encoded_paths: EncodedPathPolicy,
+    legacy_clients: bool,
+}
+
+impl RequestParser {
+    pub fn new(profile: ParserProfile) -> Self {
+        Self { profile, encoded_paths: EncodedPathPolicy::default(), legacy_clients: false }
+    }
+
+    pub fn with_legacy_clients(mut self, enabled: bool) -> Self {
+        self.legacy_clients = enabled;
+        self
+    }
+
+    pub fn with_encoded_paths(mut self, policy: EncodedPathPolicy) -> Self {
+        self.encoded_paths = policy;
+        self
+    }
+
+    pub fn parse_request(&self, input: &[u8]) -> Result<HttpRequest> {
+        let mut raw = [RawHeader::default(); MAX_HEADERS];
+        let (method, route, version, headers, len) = self.parse(input, &mut raw)?;
+        let body = Body::from(input[len..].to_vec());
+
+        Ok(HttpRequest::from_parts(method, route, version, headers, body))
+    }
+
+    pub fn parse_request_head(&self, input: &[u8]) -> Result<HttpRequest> {
+        let mut raw = [RawHeader::default(); MAX_HEADERS];
+        let (method, route, version, headers, _) = self.parse(input, &mut raw)?;
+
+        Ok(HttpRequest::from_parts(method, route, version, headers, Body::empty()))
+    }
+
+    pub fn scan_head(&self, input: &[u8]) -> Result<Status<usize>> {
+        let mut raw = [RawHeader::default(); MAX_HEADERS];
+        match self.head_parser().parse_request(input, &mut raw, false)? {
+            Status::Complete(head) => Ok(Status::Complete(head.len)),
+            Status::Partial => Ok(Status::Partial),
+        }
+    }
+
+    fn head_parser(&self) -> HeadParser {
+        HeadParser::new(self.profile).with_legacy_clients(self.legacy_clients)
+    }
+
+    fn parse<'b>(&self, input: &'b [u8], raw: &mut [RawHeader<'b>]) -> Result<(HttpMethod, Route, HttpVersion, HeaderMap, usize)> {
+        let parser = self.head_parser();
+        let head = match parser.parse_request_line(input, true)? {
+            Status::Complete(head) => head,
+            Status::Partial => return Err(ParseRequestErr::UnexpectedEndOfInput),
+        };
+
+        let method: HttpMethod = match self.profile {
+            ParserProfile::Lenient => head.method.to_ascii_uppercase().parse()?,
+            ParserProfile::Strict | ParserProfile::Standard => head.method.parse()?,
+        };
+
+        let route = Route::with_policy(head.target, self.encoded_paths)?;
+        let Some(version) = head.version else {
+            return Ok((method, route, HttpVersion::new(1, 0), HeaderMap::new(), head.len));
+        };
+
+        let version: HttpVersion = version.parse()?;
+        let (count, len) = match parser.parse_headers(&input[head.len..], raw, true)? {
+            Status::Complete(headers) => headers,
+            Status::Partial => return Err(ParseRequestErr::UnexpectedEndOfInput),
+        };
+
+        Ok((method, route, version, collect_headers(&raw[..count]), head.len + len))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ResponseParser {
+    profile: ParserProfile,
+}
+
+impl ResponseParser {
+    pub fn new(profile: ParserProfile) -> Self {
+        Self { profile }
+    }
+
+    pub fn parse_response(&self, input: &[u8]) -> Result<HttpResponse> {
+        let parser = HeadParser::new(self.profile);
+        let head = match parser.parse_status_line(input, true) {
+            Ok(Status::Complete(head)) => head,
+            Ok(Status::Partial) => return Err(ParseRequestErr::UnexpectedEndOfInput),
+            Err(RawError::InvalidStatusLine(line)) => {
+                let version = std::str::from_utf8(line)?.split(' ').next().unwrap_or_default();
+                version.parse::<HttpVersion>()?;
+                return Err(RawError::InvalidStatusLine(line).into());
+            },
+            Err(e) => return Err(e.into()),
+        };
+
+        let version: HttpVersion = head.version.parse()?;
+        let status = HttpStatusCode::from_code(head.code).ok_or_else(|| {
+            let line = &input[..head.len];
+            let line = line.strip_suffix(b"\n").unwrap_or(line);
+            ParseRequestErr::InvalidStatusLine(decode_header_value(line.strip_suffix(b"\r").unwrap_or(line)))
+        })?;
+
+        let mut raw = [RawHeader::default(); MAX_HEADERS];
+        let (count, len) = match parser.parse_headers(&input[head.len..], &mut raw, true)? {
+            Status::Complete(headers) => headers,
+            Status::Partial => return Err(ParseRequestErr::UnexpectedEndOfInput),
+        };
+
+        let headers = collect_headers(&raw[..count]);
+        let remaining = &input[head.len + len..];
+
+        let chunked = headers.get("Transfer-Encoding")
+            .is_some_and(|val| val.split(',').any(|coding| coding.trim().eq_ignore_ascii_case("chunked")));
+
+        let body = match (chunked, headers.content_length()) {
+            (true, _) => decode_chunked(remaining)?,
+            (false, Some(len)) => {
+                let len = usize::try_from(len?).map_err(|_| ParseRequestErr::UnexpectedEndOfInput)?;
+                remaining.get(..len).ok_or(ParseRequestErr::UnexpectedEndOfInput)?.to_vec()
+            },
+            (false, None) => remaining.to_vec(),
+        };
+
+        Ok(HttpResponse::from_parts(status, version, headers, body))
+    }
+}
+
+fn collect_headers(raw: &[RawHeader<'_>]) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for header in raw {
+        let value = match header.value.contains(&b'\n') {
+            true => header.value.split(|&b| b == b'\n')
+                .map(|line| decode_header_value(line.trim_ascii()))
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join(" "),
+            false => decode_header_value(header.value),
+        };
+
+        headers.append(header.name, value);
+    }
+
+    headers
+}
+
+pub fn parse_chunk_size(size_line: &str) -> Result<u64> {
+    // Whitespace is only allowed before a chunk extension, and from_str_radix alone would also accept a sign.
+    let size = match size_line.split_once(';') {
+        Some((size, _)) => size.trim_end_matches([' ', '\t']),
+        None => size_line,
+    };
+
+    if size.is_empty() || !size.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(ParseRequestErr::InvalidChunk(size_line.to_string()));
+    }
+
+    u64::from_str_radix(size, 16).map_err(|_| ParseRequestErr::InvalidChunk(size_line.to_string()))
+}
+
+fn decode_chunked(mut input: &[u8]) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let index = input.windows(2).position(|w| w == b"\r\n").ok_or(ParseRequestErr::UnexpectedEndOfInput)?;
+        let size_line = std::str::from_utf8(&input[..index])?;
+        let size = usize::try_from(parse_chunk_size(size_line)?).map_err(|_| ParseRequestErr::InvalidChunk(size_line.to_string()))?;
+        input = &input[index + 2..];
+
+        if size == 0 {
+            return Ok(body);
+        }
+
+        let chunk = input.get(..size).ok_or(ParseRequestErr::UnexpectedEndOfInput)?;
+        body.extend_from_slice(chunk);
+        let rest = &input[size..];
+        input = rest.strip_prefix(b"\r\n").ok_or_else(|| ParseRequestErr::InvalidChunk(decode_header_value(&rest[..rest.len().min(CHUNK_ERROR_CONTEXT)])))?;
+    }
+}
+
+pub(crate) fn decode_header_value(bytes: &[u8]) -> String {
+    match String::from_utf8(bytes.to_vec()) {
+        Ok(val) => val,
+        Err(_) => bytes.iter().map(|&b| b as char).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_size_accepts_hex_and_extensions() {
+        assert_eq!(parse_chunk_size("1aF").unwrap(), 0x1af);
+        assert_eq!(parse_chunk_size("10;name=value").unwrap(), 0x10);
+        assert_eq!(parse_chunk_size("10 ;name").unwrap(), 0x10);
+    }
+
+    #[test]
+    fn chunk_size_rejects_signs_and_whitespace() {
+        for line in ["+10", "-0", " 10", "10 ", "", ";ext", "0x10", "g"] {
+            assert!(matches!(parse_chunk_size(line), Err(ParseRequestErr::InvalidChunk(_))), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn scan_head_waits_for_the_blank_line() {
+        let parser = RequestParser::default();
+        let input = b"POST / HTTP/1.1\r\nContent-Length: 2\r\n\r\nhi";
+
+        assert_eq!(parser.scan_head(&input[..input.len() - 4]).unwrap(), Status::Partial);
+        assert_eq!(parser.scan_head(input).unwrap(), Status::Complete(input.len() - 2));
+    }
+
+    #[test]
+    fn too_many_headers_are_rejected() {
+        let mut input = b"GET / HTTP/1.1\r\n".to_vec();
+        for index in 0..=MAX_HEADERS {
+            input.extend(format!("X-{}: 1\r\n", index).bytes());
+        }
+        input.extend_from_slice(b"\r\n");
+
+        assert!(matches!(RequestParser::default().parse_request(&input), Err(ParseRequestErr::TooManyHeaders(MAX_HEADERS))));
+    }
+
+    #[test]
+    fn folded_headers_are_unfolded_into_one_value() {
+        let input = b"GET / HTTP/1.1\r\nX-Folded: one\r\n  two\r\n\tthree\r\n\r\n";
+        let request = RequestParser::new(ParserProfile::Standard).parse_request(input).unwrap();
+
+        assert_eq!(request.headers().get("X-Folded"), Some("one two three"));
+        assert!(matches!(RequestParser::new(ParserProfile::Strict).parse_request(input), Err(ParseRequestErr::ObsoleteLineFolding(_))));
+    }
+
+    #[test]
+    fn method_case_depends_on_profile() {
+        let input = b"get / HTTP/1.1\r\n\r\n";
+
+        assert!(matches!(RequestParser::new(ParserProfile::Standard).parse_request(input), Err(ParseRequestErr::InvalidMethod(_))));
+        assert_eq!(RequestParser::new(ParserProfile::Lenient).parse_request(input).unwrap().method(), HttpMethod::GET);
+    }
+
+    #[test]
+    fn body_bytes_are_kept_untouched() {
+        let input = b"POST / HTTP/1.1\r\nContent-Length: 4\r\n\r\n\xff\x00\xfe\r";
+        let request = RequestParser::default().parse_request(input).unwrap();
+
+        assert_eq!(request.body().in_memory(), Some(&b"\xff\x00\xfe\r"[..]));
+    }
+
+    #[test]
+    fn non_utf8_header_values_are_decoded_byte_for_byte() {
+        let request = RequestParser::default().parse_request(b"GET / HTTP/1.1\r\nX-Name: caf\xe9\r\n\r\n").unwrap();
+
+        assert_eq!(request.headers().get("X-Name"), Some("caf\u{e9}"));
+    }
+
+    #[test]
+    fn legacy_request_without_version() {
+        let input = b"GET /old\r\n";
+
+        assert!(RequestParser::default().parse_request(input).is_err());
+        let request = RequestParser::default().with_legacy_clients(true).parse_request(input).unwrap();
+        assert_eq!(request.version(), HttpVersion::new(1, 0));
+        assert_eq!(request.route().path(), "/old");
+    }
+
+    fn response(input: &[u8]) -> Result<HttpResponse> {
+        ResponseParser::default().parse_response(input)
+    }
+
+    #[test]
+    fn response_body_uses_content_length() {
+        let parsed = response(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello, extra").unwrap();
+
+        assert_eq!(parsed.status(), HttpStatusCode::OK);
+        assert_eq!(parsed.version(), HttpVersion::new(1, 1));
+        assert_eq!(parsed.body(), b"hello");
+        assert!(matches!(response(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhi"), Err(ParseRequestErr::UnexpectedEndOfInput)));
+    }
+
+    #[test]
+    fn response_body_is_decoded_from_chunks() {
+        let parsed = response(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5;ext\r\nhello\r\n1\r\n!\r\n0\r\n\r\n").unwrap();
+
+        assert_eq!(parsed.body(), b"hello!");
+    }
+
+    #[test]
+    fn response_body_without_framing_runs_to_the_end() {
+        let parsed = response(b"HTTP/1.0 200 OK\r\nConnection: close\r\n\r\nall of it").unwrap();
+
+        assert_eq!(parsed.version(), HttpVersion::new(1, 0));
+        assert_eq!(parsed.body(), b"all of it");
+    }
+
+    #[test]
+    fn unknown_status_codes_are_rejected() {
+        assert!(matches!(response(b"HTTP/1.1 299 Whatever\r\n\r\n"), Err(ParseRequestErr::InvalidStatusLine(line)) if line == "HTTP/1.1 299 Whatever"));
+        assert!(matches!(response(b"HTTP/1.1 2000 OK\r\n\r\n"), Err(ParseRequestErr::InvalidStatusLine(_))));
+        assert!(matches!(response(b"HTTX/1.1 200 OK\r\n\r\n"), Err(ParseRequestErr::InvalidVersion(_))));
+    }
+
+    #[test]
+    fn bad_chunk_error_only_keeps_a_prefix() {
+        let mut input = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n1\r\nab".to_vec();
+        input.extend(vec![b'x'; 1024 * 1024]);
+
+        match response(&input) {
+            Err(ParseRequestErr::InvalidChunk(context)) => assert_eq!(context.len(), CHUNK_ERROR_CONTEXT),
+            other => panic!("{:?}", other.map(|response| response.status())),
+        }
+    }
+}
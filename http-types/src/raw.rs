@@ -0,0 +1,437 @@
+use core::{ops::Range, str::Utf8Error};
+
+use super::{decode_header_value, ParseRequestErr, ParserProfile};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status<T> {
+    Complete(T),
+    Partial,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RawHeader<'b> {
+    pub name: &'b str,
+    pub value: &'b [u8],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawRequestHead<'b> {
+    pub method: &'b str,
+    pub target: &'b str,
+    pub version: Option<&'b str>,
+    pub headers: usize,
+    pub len: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawResponseHead<'b> {
+    pub version: &'b str,
+    pub code: u16,
+    pub reason: Option<&'b str>,
+    pub headers: usize,
+    pub len: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawError<'b> {
+    UnexpectedEndOfInput,
+    InvalidLineEnding(&'b [u8]),
+    InvalidRequestLine(&'b [u8]),
+    InvalidStatusLine(&'b [u8]),
+    InvalidHeader(&'b [u8]),
+    ObsoleteLineFolding(&'b [u8]),
+    TooManyHeaders(usize),
+    Utf8(Utf8Error),
+}
+
+impl From<Utf8Error> for RawError<'_> {
+    fn from(e: Utf8Error) -> Self {
+        Self::Utf8(e)
+    }
+}
+
+impl From<RawError<'_>> for ParseRequestErr {
+    fn from(e: RawError<'_>) -> Self {
+        match e {
+            RawError::UnexpectedEndOfInput => Self::UnexpectedEndOfInput,
+            RawError::InvalidLineEnding(line) => Self::InvalidLineEnding(decode_header_value(line)),
+            RawError::InvalidRequestLine(line) => Self::InvalidRequestHead(decode_header_value(line)),
+            RawError::InvalidStatusLine(line) => Self::InvalidStatusLine(decode_header_value(line)),
+            RawError::InvalidHeader(line) => Self::InvalidHeader(decode_header_value(line)),
+            RawError::ObsoleteLineFolding(line) => Self::ObsoleteLineFolding(decode_header_value(line)),
+            RawError::TooManyHeaders(count) => Self::TooManyHeaders(count),
+            RawError::Utf8(e) => Self::Utf8Error(e),
+        }
+    }
+}
+
+pub type RawResult<'b, T> = Result<Status<T>, RawError<'b>>;
+
+struct Cursor<'b> {
+    buf: &'b [u8],
+    pos: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct HeadParser {
+    profile: ParserProfile,
+    legacy_clients: bool,
+}
+
+impl HeadParser {
+    pub fn new(profile: ParserProfile) -> Self {
+        Self { profile, legacy_clients: false }
+    }
+
+    pub fn with_legacy_clients(mut self, enabled: bool) -> Self {
+        self.legacy_clients = enabled;
+        self
+    }
+
+    pub fn profile(&self) -> ParserProfile {
+        self.profile
+    }
+
+    pub fn parse_request<'b>(&self, buf: &'b [u8], headers: &mut [RawHeader<'b>], complete: bool) -> RawResult<'b, RawRequestHead<'b>> {
+        let head = match self.parse_request_line(buf, complete)? {
+            Status::Complete(head) if head.version.is_none() => return Ok(Status::Complete(head)),
+            Status::Complete(head) => head,
+            Status::Partial => return Ok(Status::Partial),
+        };
+
+        match self.parse_headers(&buf[head.len..], headers, complete)? {
+            Status::Complete((count, len)) => Ok(Status::Complete(RawRequestHead { headers: count, len: head.len + len, ..head })),
+            Status::Partial => Ok(Status::Partial),
+        }
+    }
+
+    pub fn parse_request_line<'b>(&self, buf: &'b [u8], complete: bool) -> RawResult<'b, RawRequestHead<'b>> {
+        let mut cursor = Cursor { buf, pos: 0 };
+        let line = match self.next_line(&mut cursor, complete)? {
+            Status::Partial => return Ok(Status::Partial),
+            Status::Complete(None) => return Err(RawError::UnexpectedEndOfInput),
+            Status::Complete(Some(line)) => &buf[line],
+        };
+
+        let text = core::str::from_utf8(line)?;
+        let mut parts = [""; 3];
+        let count = match self.profile {
+            ParserProfile::Strict => collect_parts(text.split(' '), &mut parts),
+            ParserProfile::Standard | ParserProfile::Lenient => collect_parts(text.split_whitespace(), &mut parts),
+        };
+
+        let (method, target, version) = match (count, parts) {
+            (3, [method, target, version]) => (method, target, Some(version)),
+            (2, [method @ "GET", target, _]) if self.legacy_clients => (method, target, None),
+            _ => return Err(RawError::InvalidRequestLine(line)),
+        };
+
+        Ok(Status::Complete(RawRequestHead { method, target, version, headers: 0, len: cursor.pos }))
+    }
+
+    pub fn parse_response<'b>(&self, buf: &'b [u8], headers: &mut [RawHeader<'b>], complete: bool) -> RawResult<'b, RawResponseHead<'b>> {
+        let head = match self.parse_status_line(buf, complete)? {
+            Status::Complete(head) => head,
+            Status::Partial => return Ok(Status::Partial),
+        };
+
+        match self.parse_headers(&buf[head.len..], headers, complete)? {
+            Status::Complete((count, len)) => Ok(Status::Complete(RawResponseHead { headers: count, len: head.len + len, ..head })),
+            Status::Partial => Ok(Status::Partial),
+        }
+    }
+
+    pub fn parse_status_line<'b>(&self, buf: &'b [u8], complete: bool) -> RawResult<'b, RawResponseHead<'b>> {
+        let mut cursor = Cursor { buf, pos: 0 };
+        let line = match self.next_line(&mut cursor, complete)? {
+            Status::Partial => return Ok(Status::Partial),
+            Status::Complete(None) => return Err(RawError::UnexpectedEndOfInput),
+            Status::Complete(Some(line)) => &buf[line],
+        };
+
+        let text = core::str::from_utf8(line)?;
+        let mut parts = text.splitn(3, ' ');
+        let (Some(version), Some(code)) = (parts.next(), parts.next()) else {
+            return Err(RawError::InvalidStatusLine(line));
+        };
+
+        let code = match code.len() {
+            3 => code.parse().map_err(|_| RawError::InvalidStatusLine(line))?,
+            _ => return Err(RawError::InvalidStatusLine(line)),
+        };
+
+        Ok(Status::Complete(RawResponseHead { version, code, reason: parts.next(), headers: 0, len: cursor.pos }))
+    }
+
+    pub fn parse_headers<'b>(&self, buf: &'b [u8], headers: &mut [RawHeader<'b>], complete: bool) -> RawResult<'b, (usize, usize)> {
+        let mut cursor = Cursor { buf, pos: 0 };
+        let mut count = 0;
+        let mut last_value: Option<Range<usize>> = None;
+        loop {
+            let line = match self.next_line(&mut cursor, complete)? {
+                Status::Partial => return Ok(Status::Partial),
+                Status::Complete(Some(line)) => line,
+                Status::Complete(None) if self.profile == ParserProfile::Strict => return Err(RawError::UnexpectedEndOfInput),
+                Status::Complete(None) => break,
+            };
+
+            let bytes = &buf[line.clone()];
+            if bytes.is_empty() { break; }
+
+            // Only an empty line ends the head, otherwise an intermediary could read the rest as more headers.
+            if bytes.trim_ascii().is_empty() {
+                match self.profile {
+                    ParserProfile::Strict => return Err(RawError::ObsoleteLineFolding(bytes)),
+                    ParserProfile::Standard | ParserProfile::Lenient => return Err(RawError::InvalidHeader(bytes)),
+                }
+            }
+
+            if bytes.starts_with(b" ") || bytes.starts_with(b"\t") {
+                let value = match (self.profile, &mut last_value) {
+                    (ParserProfile::Strict, _) => return Err(RawError::ObsoleteLineFolding(bytes)),
+                    (_, Some(value)) => value,
+                    (_, None) => return Err(RawError::InvalidHeader(bytes)),
+                };
+
+                value.end = line.start + bytes.trim_ascii_end().len();
+                headers[count - 1].value = &buf[value.clone()];
+                continue;
+            }
+
+            let (name, value) = self.split_header(buf, line)
+                .ok_or(RawError::InvalidHeader(bytes))?;
+
+            let slot = headers.get_mut(count).ok_or(RawError::TooManyHeaders(count))?;
+            *slot = RawHeader { name, value: &buf[value.clone()] };
+            last_value = Some(value);
+            count += 1;
+        }
+
+        Ok(Status::Complete((count, cursor.pos)))
+    }
+
+    fn next_line<'b>(&self, cursor: &mut Cursor<'b>, complete: bool) -> RawResult<'b, Option<Range<usize>>> {
+        let rest = &cursor.buf[cursor.pos..];
+        if rest.is_empty() {
+            return match complete {
+                true => Ok(Status::Complete(None)),
+                false => Ok(Status::Partial),
+            };
+        }
+
+        let start = cursor.pos;
+        let end = match rest.iter().position(|&b| b == b'\n') {
+            Some(index) => {
+                cursor.pos += index + 1;
+                start + index
+            },
+            None if !complete => return Ok(Status::Partial),
+            None if self.profile == ParserProfile::Strict => return Err(RawError::UnexpectedEndOfInput),
+            None => {
+                cursor.pos = cursor.buf.len();
+                cursor.buf.len()
+            },
+        };
+
+        match cursor.buf[start..end].strip_suffix(b"\r") {
+            Some(line) => Ok(Status::Complete(Some(start..start + line.len()))),
+            None if self.profile == ParserProfile::Strict => Err(RawError::InvalidLineEnding(&cursor.buf[start..end])),
+            None => Ok(Status::Complete(Some(start..end))),
+        }
+    }
+
+    fn split_header<'b>(&self, buf: &'b [u8], line: Range<usize>) -> Option<(&'b str, Range<usize>)> {
+        let bytes = &buf[line.clone()];
+        let index = bytes.iter().position(|&b| b == b':')?;
+        let key = core::str::from_utf8(&bytes[..index]).ok()?;
+
+        let key = match self.profile {
+            ParserProfile::Strict if !key.bytes().all(is_token_char) => return None,
+            ParserProfile::Strict | ParserProfile::Standard if key.trim_end() != key => return None,
+            ParserProfile::Strict | ParserProfile::Standard => key,
+            ParserProfile::Lenient => key.trim_end(),
+        };
+
+        if key.is_empty() {
+            return None;
+        }
+
+        let after_colon = &bytes[index + 1..];
+        let start = line.start + index + 1 + (after_colon.len() - after_colon.trim_ascii_start().len());
+        let end = (line.start + index + 1 + after_colon.trim_ascii_end().len()).max(start);
+
+        Some((key, start..end))
+    }
+}
+
+fn collect_parts<'a>(iter: impl Iterator<Item = &'a str>, parts: &mut [&'a str; 3]) -> usize {
+    let mut count = 0;
+    for part in iter {
+        if count == parts.len() {
+            return count + 1;
+        }
+
+        parts[count] = part;
+        count += 1;
+    }
+
+    count
+}
+
+fn is_token_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEAD: &[u8] = b"GET / HTTP/1.1\r\nHost: a\r\n \r\nX-Smuggled: 1\r\n\r\n";
+
+    fn parse(profile: ParserProfile, input: &[u8]) -> RawResult<'_, RawRequestHead<'_>> {
+        let mut headers = [RawHeader::default(); 8];
+        HeadParser::new(profile).parse_request(input, &mut headers, true)
+    }
+
+    #[test]
+    fn whitespace_line_does_not_end_strict_head() {
+        assert!(matches!(parse(ParserProfile::Strict, HEAD), Err(RawError::ObsoleteLineFolding(_))));
+    }
+
+    #[test]
+    fn whitespace_line_does_not_end_standard_head() {
+        assert!(matches!(parse(ParserProfile::Standard, HEAD), Err(RawError::InvalidHeader(_))));
+    }
+
+    #[test]
+    fn whitespace_line_does_not_end_lenient_head() {
+        assert!(matches!(parse(ParserProfile::Lenient, HEAD), Err(RawError::InvalidHeader(_))));
+    }
+
+    #[test]
+    fn whitespace_line_with_bare_lf_does_not_end_lenient_head() {
+        let head = b"GET / HTTP/1.1\nHost: a\n\t\nX-Smuggled: 1\n\n";
+
+        assert!(matches!(parse(ParserProfile::Lenient, head), Err(RawError::InvalidHeader(_))));
+    }
+
+    #[test]
+    fn empty_line_ends_head() {
+        for profile in [ParserProfile::Strict, ParserProfile::Standard, ParserProfile::Lenient] {
+            let head = parse(profile, b"GET / HTTP/1.1\r\nHost: a\r\n\r\nbody").unwrap();
+
+            assert!(matches!(head, Status::Complete(RawRequestHead { headers: 1, len: 27, .. })), "{:?}", profile);
+        }
+    }
+
+    #[test]
+    fn every_split_point_is_partial() {
+        let input = b"GET /a HTTP/1.1\r\nHost: a\r\nX-Folded: one\r\n two\r\n\r\n";
+        for profile in [ParserProfile::Standard, ParserProfile::Lenient] {
+            for end in 0..input.len() {
+                let mut headers = [RawHeader::default(); 8];
+                let status = HeadParser::new(profile).parse_request(&input[..end], &mut headers, false);
+
+                assert_eq!(status, Ok(Status::Partial), "{:?} at {}", profile, end);
+            }
+
+            let mut headers = [RawHeader::default(); 8];
+            let status = HeadParser::new(profile).parse_request(input, &mut headers, false);
+            assert!(matches!(status, Ok(Status::Complete(RawRequestHead { headers: 2, len, .. })) if len == input.len()));
+        }
+    }
+
+    #[test]
+    fn every_split_point_of_a_response_is_partial() {
+        let input = b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n";
+        for end in 0..input.len() {
+            let mut headers = [RawHeader::default(); 8];
+
+            assert_eq!(HeadParser::new(ParserProfile::Strict).parse_response(&input[..end], &mut headers, false), Ok(Status::Partial), "{}", end);
+        }
+    }
+
+    #[test]
+    fn too_many_headers() {
+        let mut headers = [RawHeader::default(); 2];
+        let input = b"GET / HTTP/1.1\r\nA: 1\r\nB: 2\r\nC: 3\r\n\r\n";
+
+        assert_eq!(HeadParser::default().parse_request(input, &mut headers, true), Err(RawError::TooManyHeaders(2)));
+    }
+
+    #[test]
+    fn request_line_whitespace_depends_on_profile() {
+        let input = b"GET  /  HTTP/1.1\r\n\r\n";
+
+        assert!(matches!(parse(ParserProfile::Strict, input), Err(RawError::InvalidRequestLine(_))));
+        assert!(matches!(parse(ParserProfile::Standard, input), Ok(Status::Complete(RawRequestHead { target: "/", .. }))));
+        assert!(matches!(parse(ParserProfile::Lenient, input), Ok(Status::Complete(RawRequestHead { target: "/", .. }))));
+    }
+
+    #[test]
+    fn bare_lf_is_only_rejected_by_strict() {
+        let input = b"GET / HTTP/1.1\nHost: a\n\n";
+
+        assert!(matches!(parse(ParserProfile::Strict, input), Err(RawError::InvalidLineEnding(_))));
+        assert!(matches!(parse(ParserProfile::Standard, input), Ok(Status::Complete(RawRequestHead { headers: 1, .. }))));
+        assert!(matches!(parse(ParserProfile::Lenient, input), Ok(Status::Complete(RawRequestHead { headers: 1, .. }))));
+    }
+
+    #[test]
+    fn whitespace_before_colon_is_only_accepted_by_lenient() {
+        let input = b"GET / HTTP/1.1\r\nHost : a\r\n\r\n";
+        let mut headers = [RawHeader::default(); 8];
+
+        assert!(matches!(parse(ParserProfile::Strict, input), Err(RawError::InvalidHeader(_))));
+        assert!(matches!(parse(ParserProfile::Standard, input), Err(RawError::InvalidHeader(_))));
+        assert!(HeadParser::new(ParserProfile::Lenient).parse_request(input, &mut headers, true).is_ok());
+        assert_eq!(headers[0], RawHeader { name: "Host", value: b"a" });
+    }
+
+    #[test]
+    fn invalid_header_names_are_only_rejected_by_strict() {
+        let input = b"GET / HTTP/1.1\r\nX(bad): a\r\n\r\n";
+
+        assert!(matches!(parse(ParserProfile::Strict, input), Err(RawError::InvalidHeader(_))));
+        assert!(parse(ParserProfile::Standard, input).is_ok());
+    }
+
+    #[test]
+    fn obs_fold_is_rejected_by_strict_and_joined_otherwise() {
+        let input = b"GET / HTTP/1.1\r\nX-Folded: one\r\n\ttwo\r\nHost: a\r\n\r\n";
+        assert!(matches!(parse(ParserProfile::Strict, input), Err(RawError::ObsoleteLineFolding(b"\ttwo"))));
+
+        for profile in [ParserProfile::Standard, ParserProfile::Lenient] {
+            let mut headers = [RawHeader::default(); 8];
+            let head = HeadParser::new(profile).parse_request(input, &mut headers, true);
+
+            assert!(matches!(head, Ok(Status::Complete(RawRequestHead { headers: 2, .. }))));
+            assert_eq!(headers[0], RawHeader { name: "X-Folded", value: b"one\r\n\ttwo" });
+        }
+    }
+
+    #[test]
+    fn fold_without_a_previous_header_is_invalid() {
+        assert!(matches!(parse(ParserProfile::Lenient, b"GET / HTTP/1.1\r\n folded\r\n\r\n"), Err(RawError::InvalidHeader(_))));
+    }
+
+    #[test]
+    fn header_values_may_hold_arbitrary_bytes() {
+        let mut headers = [RawHeader::default(); 8];
+        let input = b"GET / HTTP/1.1\r\nX-Name: caf\xe9\r\n\r\n";
+
+        assert!(HeadParser::new(ParserProfile::Strict).parse_request(input, &mut headers, true).is_ok());
+        assert_eq!(headers[0].value, b"caf\xe9");
+    }
+
+    #[test]
+    fn non_utf8_request_line_is_rejected() {
+        assert!(matches!(parse(ParserProfile::Lenient, b"GET /\xff HTTP/1.1\r\n\r\n"), Err(RawError::Utf8(_))));
+    }
+
+    #[test]
+    fn incomplete_input_ends_unexpectedly_in_strict() {
+        assert_eq!(parse(ParserProfile::Strict, b"GET / HTTP/1.1\r\nHost: a\r\n"), Err(RawError::UnexpectedEndOfInput));
+        assert!(matches!(parse(ParserProfile::Standard, b"GET / HTTP/1.1\r\nHost: a\r\n"), Ok(Status::Complete(RawRequestHead { headers: 1, .. }))));
+    }
+}
@@ -0,0 +1,373 @@
+use std::{fmt::Display, str::FromStr};
+
+use err_derive::Error;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{router::PathParams, uri, Body, Extensions, HeaderMap, Host, HttpVersion, RequestParser};
+
+pub type Result<T> = std::result::Result<T, ParseRequestErr>;
+
+const METHOD_OVERRIDE_HEADER: &str = "X-HTTP-Method-Override";
+const METHOD_OVERRIDE_FIELD: &str = "_method";
+const FORM_CONTENT_TYPE: &str = "application/x-www-form-urlencoded";
+const ENCODED_PATH_SEPARATORS: [&str; 4] = ["%2F", "%2f", "%2E", "%2e"];
+
+#[derive(Debug, Error)]
+pub enum ParseRequestErr {
+    #[error(display = "'{}' is not a valid http method", _0)]
+    InvalidMethod(String),
+    #[error(display = "'{}' is not a valid http version", _0)]
+    InvalidVersion(String),
+    #[error(display = "'{}' is not a valid http head", _0)]
+    InvalidRequestHead(String),
+    #[error(display = "'{}' is not a valid http status line", _0)]
+    InvalidStatusLine(String),
+    #[error(display = "'{}' is not a valid chunked body", _0)]
+    InvalidChunk(String),
+    #[error(display = "'{}' is not a valid http header", _0)]
+    InvalidHeader(String),
+    #[error(display = "'{}' is not a valid host", _0)]
+    InvalidHost(String),
+    #[error(display = "'{}' uses obsolete line folding", _0)]
+    ObsoleteLineFolding(String),
+    #[error(display = "'{}' is not terminated by CRLF", _0)]
+    InvalidLineEnding(String),
+    #[error(display = "'{}' contains an invalid percent-encoding", _0)]
+    InvalidPercentEncoding(String),
+    #[error(display = "'{}' contains an encoded slash or dot", _0)]
+    EncodedPathSeparator(String),
+    #[error(display = "Request has more than {} headers", _0)]
+    TooManyHeaders(usize),
+    #[error(display = "End of input reached unexpectedly")]
+    UnexpectedEndOfInput,
+    #[error(display = "Parse int error: {}", _0)]
+    ParseIntError(#[source] std::num::ParseIntError),
+    #[error(display = "From Utf8 error: {}", _0)]
+    FromUtf8Error(#[source] std::string::FromUtf8Error),
+    #[error(display = "Utf8 error: {}", _0)]
+    Utf8Error(#[source] std::str::Utf8Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum HttpMethod {
+    GET,
+    HEAD,
+    POST,
+    PUT,
+    DELETE,
+    CONNECT,
+    OPTIONS,
+    TRACE,
+    PATCH,
+}
+
+impl FromStr for HttpMethod {
+    type Err = ParseRequestErr;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "GET" => Ok(Self::GET),
+            "HEAD" => Ok(Self::HEAD),
+            "POST" => Ok(Self::POST),
+            "PUT" => Ok(Self::PUT),
+            "DELETE" => Ok(Self::DELETE),
+            "CONNECT" => Ok(Self::CONNECT),
+            "OPTIONS" => Ok(Self::OPTIONS),
+            "TRACE" => Ok(Self::TRACE),
+            "PATCH" => Ok(Self::PATCH),
+            _ => Err(ParseRequestErr::InvalidMethod(s.to_string()))
+        }
+    }
+}
+
+impl Display for HttpMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::GET => "GET",
+            Self::HEAD => "HEAD",
+            Self::POST => "POST",
+            Self::PUT => "PUT",
+            Self::DELETE => "DELETE",
+            Self::CONNECT => "CONNECT",
+            Self::OPTIONS => "OPTIONS",
+            Self::TRACE => "TRACE",
+            Self::PATCH => "PATCH",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum MethodOverride {
+    #[default]
+    Disabled,
+    Header,
+    FormField,
+    Any,
+}
+
+impl FromStr for MethodOverride {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "off" | "disabled" => Ok(Self::Disabled),
+            "header" => Ok(Self::Header),
+            "form" => Ok(Self::FormField),
+            "any" => Ok(Self::Any),
+            _ => Err(format!("'{}' is not a valid method override policy", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum EncodedPathPolicy {
+    #[default]
+    Decode,
+    Preserve,
+    Reject,
+}
+
+impl FromStr for EncodedPathPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "decode" => Ok(Self::Decode),
+            "preserve" => Ok(Self::Preserve),
+            "reject" => Ok(Self::Reject),
+            _ => Err(format!("'{}' is not a valid encoded path policy", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Route {
+    path: String,
+    query: Option<String>,
+}
+
+impl Route {
+    pub fn new(input: impl Display) -> Result<Self> {
+        Self::with_policy(input, EncodedPathPolicy::Decode)
+    }
+
+    pub fn with_policy(input: impl Display, policy: EncodedPathPolicy) -> Result<Self> {
+        let input = input.to_string();
+        let (path, query) = match input.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (input.as_str(), None),
+        };
+
+        let path = match policy {
+            EncodedPathPolicy::Decode => uri::percent_decode(path)?,
+            EncodedPathPolicy::Preserve => {
+                let escaped = ENCODED_PATH_SEPARATORS
+                    .iter()
+                    .fold(path.to_string(), |path, encoded| path.replace(encoded, &format!("%25{}", &encoded[1..])));
+
+                uri::percent_decode(&escaped)?
+            },
+            EncodedPathPolicy::Reject if ENCODED_PATH_SEPARATORS.iter().any(|encoded| path.contains(encoded)) => {
+                return Err(ParseRequestErr::EncodedPathSeparator(input));
+            },
+            EncodedPathPolicy::Reject => uri::percent_decode(path)?,
+        };
+
+        // The query stays encoded so that escaped '&' and '=' survive until it is split into parameters.
+        if let Some(query) = query {
+            uri::percent_decode(query)?;
+        }
+
+        Ok(Self { path, query: query.map(str::to_string) })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn query(&self) -> Option<&str> {
+        self.query.as_deref()
+    }
+
+    pub fn query_params(&self) -> impl Iterator<Item = (String, String)> + '_ {
+        self.query
+            .iter()
+            .flat_map(|query| query.split('&'))
+            .filter(|pair| !pair.is_empty())
+            .filter_map(|pair| {
+                let (key, val) = pair.split_once('=').unwrap_or((pair, ""));
+                Some((uri::percent_decode(key).ok()?, uri::percent_decode(val).ok()?))
+            })
+    }
+
+    pub fn query_param(&self, name: &str) -> Option<String> {
+        self.query_params().find(|(key, _)| key == name).map(|(_, val)| val)
+    }
+
+    pub fn normalized_path(&self) -> String {
+        uri::normalize_path(self.path())
+    }
+}
+
+impl Display for Route {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.query {
+            Some(query) => write!(f, "{}?{}", self.path, query),
+            None => write!(f, "{}", self.path),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpRequest {
+    method: HttpMethod,
+    route: Route,
+    version: HttpVersion,
+    headers: HeaderMap,
+    body: Body,
+    #[serde(skip)]
+    extensions: Extensions,
+}
+
+impl HttpRequest {
+    pub fn new(input: &[u8]) -> Result<Self> {
+        RequestParser::default().parse_request(input)
+    }
+
+    pub(crate) fn from_parts(method: HttpMethod, route: Route, version: HttpVersion, headers: HeaderMap, body: Body) -> Self {
+        Self { method, route, version, headers, body, extensions: Extensions::new() }
+    }
+
+    pub fn method(&self) -> HttpMethod {
+        self.method
+    }
+
+    pub fn route(&self) -> &Route {
+        &self.route
+    }
+
+    pub fn version(&self) -> HttpVersion {
+        self.version
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        &mut self.headers
+    }
+
+    pub fn host(&self) -> Option<Result<Host>> {
+        self.headers.get("Host").map(|val| val.trim().parse())
+    }
+
+    pub fn body(&self) -> &Body {
+        &self.body
+    }
+
+    pub fn set_body(&mut self, body: impl Into<Body>) {
+        self.body = body.into();
+    }
+
+    pub fn body_str(&self) -> Option<&str> {
+        std::str::from_utf8(self.body.in_memory()?).ok()
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        let bytes = self.body.in_memory()
+            .ok_or_else(|| serde::de::Error::custom("a body spilled to disk cannot be parsed in place"))?;
+
+        serde_json::from_slice(bytes)
+    }
+
+    pub fn apply_method_override(&mut self, policy: MethodOverride) -> bool {
+        if self.method != HttpMethod::POST {
+            return false;
+        }
+
+        let requested = match policy {
+            MethodOverride::Disabled => None,
+            MethodOverride::Header => self.override_from_header(),
+            MethodOverride::FormField => self.override_from_form(),
+            MethodOverride::Any => self.override_from_header().or_else(|| self.override_from_form()),
+        };
+
+        match requested {
+            Some(method @ (HttpMethod::PUT | HttpMethod::PATCH | HttpMethod::DELETE)) => {
+                self.method = method;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    fn override_from_header(&self) -> Option<HttpMethod> {
+        self.headers
+            .get(METHOD_OVERRIDE_HEADER)
+            .and_then(|val| val.trim().to_ascii_uppercase().parse().ok())
+    }
+
+    fn override_from_form(&self) -> Option<HttpMethod> {
+        let is_form = self.headers.content_type().is_some_and(|val| val.starts_with(FORM_CONTENT_TYPE));
+
+        if !is_form {
+            return None;
+        }
+
+        self.body_str()?
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == METHOD_OVERRIDE_FIELD)
+            .and_then(|(_, val)| uri::percent_decode(val).ok())
+            .and_then(|val| val.trim().to_ascii_uppercase().parse().ok())
+    }
+
+    pub fn path_param(&self, name: &str) -> Option<&str> {
+        self.extensions.get::<PathParams>()?.get(name)
+    }
+
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoded_question_mark_stays_in_path() {
+        let route = Route::new("/a%3Fb?x=1").unwrap();
+
+        assert_eq!(route.path(), "/a?b");
+        assert_eq!(route.query(), Some("x=1"));
+    }
+
+    #[test]
+    fn query_params_are_decoded_after_splitting() {
+        let route = Route::new("/search?q=a%26b%3Dc&empty&name=x%20y").unwrap();
+
+        assert_eq!(route.query_param("q").as_deref(), Some("a&b=c"));
+        assert_eq!(route.query_param("empty").as_deref(), Some(""));
+        assert_eq!(route.query_param("name").as_deref(), Some("x y"));
+        assert_eq!(route.query_param("missing"), None);
+    }
+
+    #[test]
+    fn invalid_query_encoding_is_rejected() {
+        assert!(matches!(Route::new("/?q=%zz"), Err(ParseRequestErr::InvalidPercentEncoding(_))));
+    }
+
+    #[test]
+    fn display_keeps_query_encoded() {
+        assert_eq!(Route::new("/a%20b?q=a%26b").unwrap().to_string(), "/a b?q=a%26b");
+    }
+}
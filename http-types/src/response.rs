@@ -0,0 +1,434 @@
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{deserialize_bytes, serialize_bytes, Extensions, HeaderMap, HttpVersion};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[allow(dead_code)]
+pub enum HttpStatusCode {
+    Continue = 100,
+    SwitchingProtocols = 101,
+    Processing = 102,
+    EarlyHints = 103,
+    OK = 200,
+    Created = 201,
+    Accepted = 202,
+    NonAuthoritativeInformation = 203,
+    NoContent = 204,
+    ResetContent = 205,
+    PartialContent = 206,
+    MultiStatus = 207,
+    AlreadyReported = 208,
+    ImUsed = 226,
+    MultipleChoices = 300,
+    MovedPermanently = 301,
+    Found = 302,
+    SeeOther = 303,
+    NotModified = 304,
+    UseProxy = 305,
+    Unused = 306,
+    TemporaryRedirect = 307,
+    PermanentRedirect = 308,
+    BadRequest = 400,
+    Unauthorized = 401,
+    PaymentRequired = 402,
+    Forbidden = 403,
+    NotFound = 404,
+    MethodNotAllowed = 405,
+    NotAcceptable = 406,
+    ProxyAuthenticationRequired = 407,
+    RequestTimeout = 408,
+    Conflict = 409,
+    Gone = 410,
+    LengthRequired = 411,
+    PreconditionFailed = 412,
+    ContentTooLarge = 413,
+    UriTooLong = 414,
+    UnsupportedMediaType = 415,
+    RangeNotSatisfiable = 416,
+    ExpectationFailed = 417,
+    ImATeapot = 418,
+    MisdirectedRequest = 421,
+    UnprocessableContent = 422,
+    Locked = 423,
+    FailedDependency = 424,
+    TooEarly = 425,
+    UpgradeRequired = 426,
+    PreconditionRequired = 428,
+    TooManyRequests = 429,
+    RequestHeaderFieldsTooLarge = 431,
+    UnavailableForLegalReasons = 451,
+    InternalServerError = 500,
+    NotImplemented = 501,
+    BadGateway = 502,
+    ServiceUnavailable = 503,
+    GatewayTimeout = 504,
+    HTTPVersionNotSupported = 505,
+    VariantAlsoNegotiates = 506,
+    InsufficientStorage = 507,
+    LoopDetected = 508,
+    NotExtended = 510,
+    NetworkAuthenticationRequired = 511,
+}
+
+impl HttpStatusCode {
+    pub fn from_code(code: u16) -> Option<Self> {
+        match code {
+            100 => Some(Self::Continue),
+            101 => Some(Self::SwitchingProtocols),
+            102 => Some(Self::Processing),
+            103 => Some(Self::EarlyHints),
+            200 => Some(Self::OK),
+            201 => Some(Self::Created),
+            202 => Some(Self::Accepted),
+            203 => Some(Self::NonAuthoritativeInformation),
+            204 => Some(Self::NoContent),
+            205 => Some(Self::ResetContent),
+            206 => Some(Self::PartialContent),
+            207 => Some(Self::MultiStatus),
+            208 => Some(Self::AlreadyReported),
+            226 => Some(Self::ImUsed),
+            300 => Some(Self::MultipleChoices),
+            301 => Some(Self::MovedPermanently),
+            302 => Some(Self::Found),
+            303 => Some(Self::SeeOther),
+            304 => Some(Self::NotModified),
+            305 => Some(Self::UseProxy),
+            306 => Some(Self::Unused),
+            307 => Some(Self::TemporaryRedirect),
+            308 => Some(Self::PermanentRedirect),
+            400 => Some(Self::BadRequest),
+            401 => Some(Self::Unauthorized),
+            402 => Some(Self::PaymentRequired),
+            403 => Some(Self::Forbidden),
+            404 => Some(Self::NotFound),
+            405 => Some(Self::MethodNotAllowed),
+            406 => Some(Self::NotAcceptable),
+            407 => Some(Self::ProxyAuthenticationRequired),
+            408 => Some(Self::RequestTimeout),
+            409 => Some(Self::Conflict),
+            410 => Some(Self::Gone),
+            411 => Some(Self::LengthRequired),
+            412 => Some(Self::PreconditionFailed),
+            413 => Some(Self::ContentTooLarge),
+            414 => Some(Self::UriTooLong),
+            415 => Some(Self::UnsupportedMediaType),
+            416 => Some(Self::RangeNotSatisfiable),
+            417 => Some(Self::ExpectationFailed),
+            418 => Some(Self::ImATeapot),
+            421 => Some(Self::MisdirectedRequest),
+            422 => Some(Self::UnprocessableContent),
+            423 => Some(Self::Locked),
+            424 => Some(Self::FailedDependency),
+            425 => Some(Self::TooEarly),
+            426 => Some(Self::UpgradeRequired),
+            428 => Some(Self::PreconditionRequired),
+            429 => Some(Self::TooManyRequests),
+            431 => Some(Self::RequestHeaderFieldsTooLarge),
+            451 => Some(Self::UnavailableForLegalReasons),
+            500 => Some(Self::InternalServerError),
+            501 => Some(Self::NotImplemented),
+            502 => Some(Self::BadGateway),
+            503 => Some(Self::ServiceUnavailable),
+            504 => Some(Self::GatewayTimeout),
+            505 => Some(Self::HTTPVersionNotSupported),
+            506 => Some(Self::VariantAlsoNegotiates),
+            507 => Some(Self::InsufficientStorage),
+            508 => Some(Self::LoopDetected),
+            510 => Some(Self::NotExtended),
+            511 => Some(Self::NetworkAuthenticationRequired),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> u16 {
+        self as u16
+    }
+
+    pub fn get_readable_name(self) -> &'static str {
+        match self {
+            Self::Continue => "Continue",
+            Self::SwitchingProtocols => "Switching Protocols",
+            Self::Processing => "Processing",
+            Self::EarlyHints => "Early Hints",
+            Self::OK => "OK",
+            Self::Created => "Created",
+            Self::Accepted => "Accepted",
+            Self::NonAuthoritativeInformation => "Non-Authoritative Information",
+            Self::NoContent => "No Content",
+            Self::ResetContent => "Reset Content",
+            Self::PartialContent => "Partial Content",
+            Self::MultiStatus => "Multi-Status",
+            Self::AlreadyReported => "Already Reported",
+            Self::ImUsed => "IM Used",
+            Self::MultipleChoices => "Multiple Choices",
+            Self::MovedPermanently => "Moved Permanently",
+            Self::Found => "Found",
+            Self::SeeOther => "See Other",
+            Self::NotModified => "Not Modified",
+            Self::UseProxy => "Use Proxy",
+            Self::Unused => "unused",
+            Self::TemporaryRedirect => "Temporary Redirect",
+            Self::PermanentRedirect => "Permanent Redirect",
+            Self::BadRequest => "Bad Request",
+            Self::Unauthorized => "Unauthorized",
+            Self::PaymentRequired => "Payment Required",
+            Self::Forbidden => "Forbidden",
+            Self::NotFound => "Not Found",
+            Self::MethodNotAllowed => "Method Not Allowed",
+            Self::NotAcceptable => "Not Acceptable",
+            Self::ProxyAuthenticationRequired => "Proxy Authentication Required",
+            Self::RequestTimeout => "Request Timeout",
+            Self::Conflict => "Conflict",
+            Self::Gone => "Gone",
+            Self::LengthRequired => "Length Required",
+            Self::PreconditionFailed => "Precondition Failed",
+            Self::ContentTooLarge => "Content Too Large",
+            Self::UriTooLong => "URI Too Long",
+            Self::UnsupportedMediaType => "Unsupported Media Type",
+            Self::RangeNotSatisfiable => "Range Not Satisfiable",
+            Self::ExpectationFailed => "Expectation Failed",
+            Self::ImATeapot => "I'm a teapot",
+            Self::MisdirectedRequest => "Misdirected Request",
+            Self::UnprocessableContent => "Unprocessable Content",
+            Self::Locked => "Locked",
+            Self::FailedDependency => "Failed Dependency",
+            Self::TooEarly => "Too Early",
+            Self::UpgradeRequired => "Upgrade Required",
+            Self::PreconditionRequired => "Precondition Required",
+            Self::TooManyRequests => "Too Many Requests",
+            Self::RequestHeaderFieldsTooLarge => "Request Header Fields Too Large",
+            Self::UnavailableForLegalReasons => "Unavailable For Legal Reasons",
+            Self::InternalServerError => "Internal Server Error",
+            Self::NotImplemented => "Not Implemented",
+            Self::BadGateway => "Bad Gateway",
+            Self::ServiceUnavailable => "Service Unavailable",
+            Self::GatewayTimeout => "Gateway Timeout",
+            Self::HTTPVersionNotSupported => "HTTP Version Not Supported",
+            Self::VariantAlsoNegotiates => "Variant Also Negotiates",
+            Self::InsufficientStorage => "Insufficient Storage",
+            Self::LoopDetected => "Loop Detected",
+            Self::NotExtended => "Not Extended",
+            Self::NetworkAuthenticationRequired => "Network Authentication Required",
+        }
+    }
+}
+
+impl Serialize for HttpStatusCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u16(self.code())
+    }
+}
+
+impl<'de> Deserialize<'de> for HttpStatusCode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = u16::deserialize(deserializer)?;
+        Self::from_code(code)
+            .ok_or_else(|| serde::de::Error::custom(format!("'{}' is not a known http status code", code)))
+    }
+}
+
+impl std::fmt::Display for HttpStatusCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", *self as usize, self.get_readable_name())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpResponse {
+    status: HttpStatusCode,
+    version: HttpVersion,
+    headers: HeaderMap,
+    #[serde(serialize_with = "serialize_bytes", deserialize_with = "deserialize_bytes")]
+    body: Vec<u8>,
+    #[serde(skip)]
+    extensions: Extensions,
+}
+
+impl HttpResponse {
+    pub fn new(status: HttpStatusCode, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            version: HttpVersion::new(1, 1),
+            headers: HeaderMap::new(),
+            body: body.into(),
+            extensions: Extensions::new(),
+        }
+    }
+
+    pub(crate) fn from_parts(status: HttpStatusCode, version: HttpVersion, headers: HeaderMap, body: Vec<u8>) -> Self {
+        Self { status, version, headers, body, extensions: Extensions::new() }
+    }
+
+    pub fn builder() -> HttpResponseBuilder {
+        HttpResponseBuilder::new()
+    }
+
+    pub fn ok(body: impl Into<Vec<u8>>) -> Self {
+        Self::new(HttpStatusCode::OK, body)
+    }
+
+    pub fn not_found() -> Self {
+        Self::new(HttpStatusCode::NotFound, HttpStatusCode::NotFound.get_readable_name())
+    }
+
+    pub fn json<T: Serialize + ?Sized>(value: &T) -> serde_json::Result<Self> {
+        Ok(Self::builder()
+            .header("Content-Type", "application/json")
+            .body(serde_json::to_string(value)?))
+    }
+
+    pub fn redirect(location: impl std::fmt::Display) -> Self {
+        Self::builder()
+            .status(HttpStatusCode::Found)
+            .header("Location", location)
+            .build()
+    }
+
+    pub fn im_a_teapot(body: impl Into<Vec<u8>>) -> Self {
+        Self::new(HttpStatusCode::ImATeapot, body)
+    }
+
+    pub fn status(&self) -> HttpStatusCode {
+        self.status
+    }
+
+    pub fn set_status(&mut self, status: HttpStatusCode) {
+        self.status = status;
+    }
+
+    pub fn version(&self) -> HttpVersion {
+        self.version
+    }
+
+    pub fn set_version(&mut self, version: HttpVersion) {
+        self.version = version;
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        &mut self.headers
+    }
+
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    pub fn body_str(&self) -> Option<&str> {
+        std::str::from_utf8(&self.body).ok()
+    }
+
+    pub fn body_json<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_slice(&self.body)
+    }
+
+    pub fn set_body(&mut self, body: impl Into<Vec<u8>>) {
+        self.body = body.into();
+    }
+
+    pub fn fill_content_length(&mut self) {
+        let has_body = !matches!(self.status as usize, 100..=199 | 204 | 304);
+        if has_body && !self.headers.contains_key("Content-Length") && !self.headers.contains_key("Transfer-Encoding") {
+            self.headers.insert("Content-Length", self.body.len());
+        }
+    }
+
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    pub fn head_bytes(&self) -> Vec<u8> {
+        let mut bytes = format!("{} {}\r\n", self.version, self.status).into_bytes();
+        for (key, val) in self.headers.iter() {
+            bytes.extend_from_slice(format!("{}: {}\r\n", key, val).as_bytes());
+        }
+
+        bytes.extend_from_slice(b"\r\n");
+        bytes
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.head_bytes();
+        bytes.extend_from_slice(&self.body);
+        bytes
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpResponseBuilder {
+    response: HttpResponse,
+}
+
+impl HttpResponseBuilder {
+    pub fn new() -> Self {
+        Self { response: HttpResponse::new(HttpStatusCode::OK, "") }
+    }
+
+    pub fn status(mut self, status: HttpStatusCode) -> Self {
+        self.response.status = status;
+        self
+    }
+
+    pub fn version(mut self, version: HttpVersion) -> Self {
+        self.response.version = version;
+        self
+    }
+
+    pub fn header(mut self, key: impl std::fmt::Display, val: impl std::fmt::Display) -> Self {
+        self.response.headers.insert(key, val);
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> HttpResponse {
+        self.response.body = body.into();
+        self.response
+    }
+
+    pub fn build(self) -> HttpResponse {
+        self.response
+    }
+}
+
+impl Default for HttpResponseBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Display for HttpResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}\r\n", self.version, self.status)?;
+        for (key, val) in self.headers.iter() {
+            write!(f, "{}: {}\r\n", key, val)?;
+        }
+
+        write!(f, "\r\n{}", String::from_utf8_lossy(&self.body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redirect_cannot_split_the_response() {
+        let response = HttpResponse::redirect("/next\r\nSet-Cookie: session=stolen\r\n\r\nbody");
+        let head = response.head_bytes();
+
+        assert_eq!(response.headers().get("Location"), Some("/next  Set-Cookie: session=stolen    body"));
+        assert_eq!(head.windows(2).filter(|w| w == b"\r\n").count(), 3);
+    }
+
+    #[test]
+    fn header_names_are_sanitized() {
+        let response = HttpResponse::builder().header("X-A\r\nX-B", "1").build();
+
+        assert_eq!(response.headers().iter().next(), Some(("X-A  X-B", "1")));
+    }
+}
@@ -0,0 +1,264 @@
+use std::{collections::HashMap, str::FromStr, sync::Arc};
+
+use crate::{middleware::{Middleware, MiddlewareFn, Next}, HttpMethod, HttpRequest, HttpResponse};
+
+pub(crate) type HandlerFn = Arc<dyn Fn(&HttpRequest) -> HttpResponse + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl FromStr for Priority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "low" => Ok(Self::Low),
+            "normal" => Ok(Self::Normal),
+            "high" => Ok(Self::High),
+            _ => Err(format!("'{}' is not a valid priority", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Param(String),
+    Wildcard(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PathParams {
+    params: HashMap<String, String>,
+}
+
+impl PathParams {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.params.get(name).map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.params.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+}
+
+#[derive(Clone)]
+struct Route {
+    method: HttpMethod,
+    path: String,
+    segments: Vec<Segment>,
+    handler: HandlerFn,
+    middleware: Vec<MiddlewareFn>,
+    priority: Priority,
+}
+
+impl Route {
+    fn matches(&self, path: &str) -> Option<PathParams> {
+        let mut params = PathParams::default();
+        let mut parts = path.split('/');
+        for segment in &self.segments {
+            let part = parts.next()?;
+            match segment {
+                Segment::Literal(literal) if literal == part => {},
+                Segment::Literal(_) => return None,
+                Segment::Param(_) if part.is_empty() => return None,
+                Segment::Param(name) => { params.params.insert(name.clone(), part.to_string()); },
+                Segment::Wildcard(name) => {
+                    let rest = std::iter::once(part).chain(parts.by_ref()).collect::<Vec<_>>().join("/");
+                    params.params.insert(name.clone(), rest);
+                },
+            }
+        }
+
+        parts.next().is_none().then_some(params)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Router {
+    routes: Vec<Route>,
+    middleware: Vec<MiddlewareFn>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self { routes: Vec::new(), middleware: Vec::new() }
+    }
+
+    pub fn middleware(&mut self, middleware: impl Middleware) -> &mut Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    pub fn route_middleware(&mut self, method: HttpMethod, path: impl std::fmt::Display, middleware: impl Middleware) -> &mut Self {
+        let middleware: MiddlewareFn = Arc::new(middleware);
+        self.update_routes(method, path, "middleware", |route| route.middleware.push(middleware.clone()))
+    }
+
+    pub fn route_priority(&mut self, method: HttpMethod, path: impl std::fmt::Display, priority: Priority) -> &mut Self {
+        self.update_routes(method, path, "priority", |route| route.priority = priority)
+    }
+
+    fn update_routes(&mut self, method: HttpMethod, path: impl std::fmt::Display, setting: &str, update: impl FnMut(&mut Route)) -> &mut Self {
+        let path = path.to_string();
+        let mut routes = self.routes.iter_mut()
+            .filter(|route| route.method == method && route.path == path)
+            .peekable();
+
+        if routes.peek().is_none() {
+            log::warn!("No {:?} route is registered for '{}', so its {} is never used", method, path, setting);
+        }
+
+        routes.for_each(update);
+        self
+    }
+
+    pub fn route<F>(&mut self, method: HttpMethod, path: impl std::fmt::Display, handler: F) -> &mut Self
+    where
+        F: Fn(&HttpRequest) -> HttpResponse + Send + Sync + 'static,
+    {
+        let path = path.to_string();
+        let segments = path.split('/')
+            .map(|segment| match (segment.strip_prefix(':'), segment.strip_prefix('*')) {
+                (Some(name), _) => Segment::Param(name.to_string()),
+                (_, Some(name)) => Segment::Wildcard(name.to_string()),
+                _ => Segment::Literal(segment.to_string()),
+            })
+            .collect();
+
+        self.routes.push(Route { method, path, segments, handler: Arc::new(handler), middleware: Vec::new(), priority: Priority::default() });
+        self
+    }
+
+    pub fn get<F>(&mut self, path: impl std::fmt::Display, handler: F) -> &mut Self
+    where
+        F: Fn(&HttpRequest) -> HttpResponse + Send + Sync + 'static,
+    {
+        self.route(HttpMethod::GET, path, handler)
+    }
+
+    pub fn post<F>(&mut self, path: impl std::fmt::Display, handler: F) -> &mut Self
+    where
+        F: Fn(&HttpRequest) -> HttpResponse + Send + Sync + 'static,
+    {
+        self.route(HttpMethod::POST, path, handler)
+    }
+
+    pub fn put<F>(&mut self, path: impl std::fmt::Display, handler: F) -> &mut Self
+    where
+        F: Fn(&HttpRequest) -> HttpResponse + Send + Sync + 'static,
+    {
+        self.route(HttpMethod::PUT, path, handler)
+    }
+
+    pub fn patch<F>(&mut self, path: impl std::fmt::Display, handler: F) -> &mut Self
+    where
+        F: Fn(&HttpRequest) -> HttpResponse + Send + Sync + 'static,
+    {
+        self.route(HttpMethod::PATCH, path, handler)
+    }
+
+    pub fn delete<F>(&mut self, path: impl std::fmt::Display, handler: F) -> &mut Self
+    where
+        F: Fn(&HttpRequest) -> HttpResponse + Send + Sync + 'static,
+    {
+        self.route(HttpMethod::DELETE, path, handler)
+    }
+
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    pub fn priority(&self, request: &HttpRequest) -> Priority {
+        self.find(request).map_or(Priority::default(), |(route, _)| route.priority)
+    }
+
+    pub fn check_head(&self, request: &mut HttpRequest) -> Option<HttpResponse> {
+        let (route, params) = self.find(request)?;
+        request.extensions_mut().insert(params);
+        self.middleware.iter()
+            .chain(&route.middleware)
+            .find_map(|middleware| middleware.check_head(request))
+    }
+
+    pub async fn handle(&self, request: &mut HttpRequest) -> HttpResponse {
+        let next = match self.find(request) {
+            Some((route, params)) => {
+                request.extensions_mut().insert(params);
+                Next::new(&self.middleware, &route.middleware, Some(&route.handler))
+            },
+            None => Next::new(&self.middleware, &[], None),
+        };
+
+        next.run(request).await
+    }
+
+    fn find(&self, request: &HttpRequest) -> Option<(&Route, PathParams)> {
+        let path = request.route().normalized_path();
+        let find = |method| self.routes.iter()
+            .filter(|route| route.method == method)
+            .find_map(|route| route.matches(&path).map(|params| (route, params)));
+
+        // The server never writes a body for HEAD, so a GET route can answer it.
+        match request.method() {
+            HttpMethod::HEAD => find(HttpMethod::HEAD).or_else(|| find(HttpMethod::GET)),
+            method => find(method),
+        }
+    }
+}
+
+impl std::fmt::Debug for Router {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.routes.iter().map(|route| format!("{:?} {}", route.method, route.path)))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, path: &str) -> HttpRequest {
+        HttpRequest::new(format!("{} {} HTTP/1.1\r\nHost: a\r\n\r\n", method, path).as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn head_falls_back_to_get_route() {
+        let mut router = Router::new();
+        router.get("/static/*path", |_| HttpResponse::ok("")).route_priority(HttpMethod::GET, "/static/*path", Priority::High);
+
+        assert_eq!(router.priority(&request("HEAD", "/static/a/b.txt")), Priority::High);
+        assert_eq!(router.priority(&request("HEAD", "/missing")), Priority::Normal);
+    }
+
+    #[test]
+    fn head_route_takes_precedence_over_get() {
+        let mut router = Router::new();
+        router.get("/", |_| HttpResponse::ok("")).route_priority(HttpMethod::GET, "/", Priority::Low);
+        router.route(HttpMethod::HEAD, "/", |_| HttpResponse::ok("")).route_priority(HttpMethod::HEAD, "/", Priority::High);
+
+        assert_eq!(router.priority(&request("HEAD", "/")), Priority::High);
+    }
+
+    #[test]
+    fn other_methods_do_not_fall_back_to_get() {
+        let mut router = Router::new();
+        router.get("/", |_| HttpResponse::ok("")).route_priority(HttpMethod::GET, "/", Priority::High);
+
+        assert_eq!(router.priority(&request("POST", "/")), Priority::Normal);
+    }
+}
@@ -0,0 +1,47 @@
+use super::{ParseRequestErr, Result};
+
+pub fn percent_decode(input: &str) -> Result<String> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        match bytes[index] {
+            b'%' => {
+                let byte = bytes.get(index + 1..index + 3)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| ParseRequestErr::InvalidPercentEncoding(input.to_string()))?;
+
+                decoded.push(byte);
+                index += 3;
+            },
+            byte => {
+                decoded.push(byte);
+                index += 1;
+            },
+        }
+    }
+
+    Ok(String::from_utf8(decoded)?)
+}
+
+pub fn normalize_path(path: &str) -> String {
+    let mut segments = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => (),
+            ".." => { segments.pop(); },
+            segment => segments.push(segment),
+        }
+    }
+
+    let mut normalized = format!("/{}", segments.join("/"));
+    let ends_in_directory = path.ends_with('/') || path.ends_with("/.") || path.ends_with("/..");
+    if ends_in_directory && !segments.is_empty() {
+        normalized.push('/');
+    }
+
+    normalized
+}
@@ -0,0 +1,135 @@
+use std::{net::SocketAddr, str::FromStr, time::Duration};
+
+use crate::models::{HttpMethod, HttpRequest, HttpStatusCode, HttpVersion};
+#[cfg(feature = "geoip")]
+use crate::geoip::GeoInfo;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessLogEntry {
+    pub client: SocketAddr,
+    pub request_line: Option<(HttpMethod, String, HttpVersion)>,
+    pub status: Option<HttpStatusCode>,
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+    pub read_time: Duration,
+    pub handle_time: Duration,
+    pub write_time: Duration,
+    pub location: Option<String>,
+}
+
+impl AccessLogEntry {
+    pub fn new(client: SocketAddr, request: Option<&HttpRequest>) -> Self {
+        Self {
+            client,
+            request_line: request.map(|request| (request.method(), request.route().path().to_string(), request.version())),
+            status: None,
+            bytes_read: 0,
+            bytes_written: 0,
+            read_time: Duration::ZERO,
+            handle_time: Duration::ZERO,
+            write_time: Duration::ZERO,
+            #[cfg(feature = "geoip")]
+            location: request.and_then(GeoInfo::from_request).map(ToString::to_string),
+            #[cfg(not(feature = "geoip"))]
+            location: None,
+        }
+    }
+
+    pub fn total_time(&self) -> Duration {
+        self.read_time + self.handle_time + self.write_time
+    }
+}
+
+impl std::fmt::Display for AccessLogEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ", self.client)?;
+
+        match &self.request_line {
+            Some((method, path, version)) => write!(f, "\"{} {} {}\" ", method, path, version)?,
+            None => write!(f, "\"-\" ")?,
+        }
+
+        match self.status {
+            Some(status) => write!(f, "{} ", status as usize)?,
+            None => write!(f, "- ")?,
+        }
+
+        write!(
+            f,
+            "in={} out={} read={:.3}ms handle={:.3}ms write={:.3}ms",
+            self.bytes_read,
+            self.bytes_written,
+            self.read_time.as_secs_f64() * 1000.0,
+            self.handle_time.as_secs_f64() * 1000.0,
+            self.write_time.as_secs_f64() * 1000.0
+        )?;
+
+        match &self.location {
+            Some(location) => write!(f, " geo={}", location),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AccessLogLevel {
+    #[default]
+    Full,
+    ErrorsOnly,
+    Off,
+}
+
+impl FromStr for AccessLogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "full" | "on" => Ok(Self::Full),
+            "errors" => Ok(Self::ErrorsOnly),
+            "off" => Ok(Self::Off),
+            _ => Err(format!("'{}' is not a valid access log level", s)),
+        }
+    }
+}
+
+impl AccessLogLevel {
+    pub fn allows(self, status: Option<HttpStatusCode>) -> bool {
+        match self {
+            Self::Full => true,
+            Self::ErrorsOnly => status.is_none_or(|status| status.code() >= 400),
+            Self::Off => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessLogRules {
+    routes: Vec<(String, AccessLogLevel)>,
+}
+
+impl AccessLogRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn route(mut self, path: impl std::fmt::Display, level: AccessLogLevel) -> Self {
+        self.routes.push((path.to_string(), level));
+        self
+    }
+
+    pub fn level(&self, path: &str) -> AccessLogLevel {
+        self.routes
+            .iter()
+            .filter(|(route, _)| matches_route(route, path))
+            .max_by_key(|(route, _)| route.len())
+            .map(|(_, level)| *level)
+            .unwrap_or_default()
+    }
+}
+
+fn matches_route(route: &str, path: &str) -> bool {
+    match route.strip_suffix("/*") {
+        Some(prefix) => path == prefix || path.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('/')),
+        None => path == route,
+    }
+}
@@ -0,0 +1,202 @@
+use std::{collections::VecDeque, str::FromStr, sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex}, time::Duration};
+
+use tokio::sync::oneshot;
+
+pub use crate::router::Priority;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum QueuePolicy {
+    #[default]
+    Fifo,
+    Priority,
+}
+
+impl FromStr for QueuePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "fifo" => Ok(Self::Fifo),
+            "priority" => Ok(Self::Priority),
+            _ => Err(format!("'{}' is not a valid queue policy", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdmissionConfig {
+    pub max_concurrent: Option<usize>,
+    pub reserved: usize,
+    pub queue_depth: usize,
+    pub queue_timeout: Duration,
+    pub policy: QueuePolicy,
+}
+
+impl Default for AdmissionConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: None,
+            reserved: 0,
+            queue_depth: 0,
+            queue_timeout: Duration::from_secs(1),
+            policy: QueuePolicy::default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Waiter {
+    priority: Priority,
+    sender: oneshot::Sender<AdmissionPermit>,
+}
+
+#[derive(Debug, Default)]
+struct AdmissionState {
+    active: usize,
+    queue: VecDeque<Waiter>,
+}
+
+#[derive(Debug, Default)]
+pub struct AdmissionControl {
+    config: AdmissionConfig,
+    state: Mutex<AdmissionState>,
+    shed: AtomicU64,
+}
+
+impl AdmissionControl {
+    pub fn new(config: AdmissionConfig) -> Self {
+        Self { config, state: Mutex::default(), shed: AtomicU64::new(0) }
+    }
+
+    pub fn config(&self) -> &AdmissionConfig {
+        &self.config
+    }
+
+    pub async fn admit(self: &Arc<Self>, priority: Priority) -> Option<AdmissionPermit> {
+        let mut receiver = {
+            let mut state = self.lock();
+            if self.limit(priority).is_none_or(|limit| state.active < limit) {
+                state.active += 1;
+                return Some(AdmissionPermit { control: self.clone() });
+            }
+
+            state.queue.retain(|waiter| !waiter.sender.is_closed());
+            let queue_full = state.queue.len() >= self.config.queue_depth && !self.displace(&mut state, priority);
+            if priority == Priority::Low || queue_full {
+                drop(state);
+                self.shed.fetch_add(1, Ordering::Relaxed);
+                return None;
+            }
+
+            let (sender, receiver) = oneshot::channel();
+            state.queue.push_back(Waiter { priority, sender });
+            receiver
+        };
+
+        let permit = match tokio::time::timeout(self.config.queue_timeout, &mut receiver).await {
+            Ok(permit) => permit.ok(),
+            Err(_) => receiver.try_recv().ok(),
+        };
+
+        if permit.is_none() {
+            drop(receiver);
+            self.lock().queue.retain(|waiter| !waiter.sender.is_closed());
+            self.shed.fetch_add(1, Ordering::Relaxed);
+        }
+
+        permit
+    }
+
+    pub fn active(&self) -> usize {
+        self.lock().active
+    }
+
+    pub fn queued(&self) -> usize {
+        self.lock().queue.len()
+    }
+
+    pub fn shed(&self) -> u64 {
+        self.shed.load(Ordering::Relaxed)
+    }
+
+    fn limit(&self, priority: Priority) -> Option<usize> {
+        let max = self.config.max_concurrent?;
+        match priority {
+            Priority::High => Some(max),
+            Priority::Normal | Priority::Low => Some(max.saturating_sub(self.config.reserved)),
+        }
+    }
+
+    fn displace(&self, state: &mut AdmissionState, priority: Priority) -> bool {
+        if self.config.policy != QueuePolicy::Priority {
+            return false;
+        }
+
+        let lowest = state.queue.iter()
+            .enumerate()
+            .rev()
+            .min_by_key(|(_, waiter)| waiter.priority)
+            .filter(|(_, waiter)| waiter.priority < priority)
+            .map(|(index, _)| index);
+
+        match lowest {
+            Some(index) => state.queue.remove(index).is_some(),
+            None => false,
+        }
+    }
+
+    fn release(self: &Arc<Self>) {
+        let next = {
+            let mut state = self.lock();
+            let active = state.active - 1;
+            let mut eligible = state.queue.iter()
+                .enumerate()
+                .filter(|(_, waiter)| self.limit(waiter.priority).is_none_or(|limit| active < limit));
+
+            let index = match self.config.policy {
+                QueuePolicy::Fifo => eligible.next(),
+                QueuePolicy::Priority => eligible.rev().max_by_key(|(_, waiter)| waiter.priority),
+            }.map(|(index, _)| index);
+
+            match index.and_then(|index| state.queue.remove(index)) {
+                Some(waiter) => waiter,
+                None => {
+                    state.active -= 1;
+                    return;
+                },
+            }
+        };
+
+        // A waiter that gave up drops the returned permit, which hands the slot to the next one.
+        let _ = next.sender.send(AdmissionPermit { control: self.clone() });
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, AdmissionState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl std::fmt::Display for AdmissionControl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (active, queued) = {
+            let state = self.lock();
+            (state.active, state.queue.len())
+        };
+
+        match self.config.max_concurrent {
+            Some(max) => write!(f, "{}/{} active, {} queued, {} shed", active, max, queued, self.shed()),
+            None => write!(f, "{} active, unlimited", active),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AdmissionPermit {
+    control: Arc<AdmissionControl>,
+}
+
+impl Drop for AdmissionPermit {
+    fn drop(&mut self) {
+        self.control.release();
+    }
+}
@@ -0,0 +1,65 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    config::{self, Env, Options},
+    console::{run_console, shutdown_on_ctrl_c},
+    errors::Error,
+    scheduler::Scheduler,
+    server::Server,
+    statsd::StatsdExporter,
+};
+
+const PENALTY_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+pub async fn run(env: &Env, options: &Options) -> Result<(), Error> {
+    let address = config::host_addr(env, options);
+    let context = config::server_context(env, options);
+
+    let penalties = context.penalties.clone();
+    let mut scheduler = Scheduler::new();
+    scheduler.every("penalty sweep", PENALTY_SWEEP_INTERVAL, move || {
+        let penalties = penalties.clone();
+        async move { penalties.sweep() }
+    });
+
+    if let Some(config) = config::statsd_config(env) {
+        match StatsdExporter::connect(config).await {
+            Ok(exporter) => {
+                let exporter = Arc::new(exporter);
+                let context = context.clone();
+                scheduler.every("statsd export", exporter.config().interval, move || {
+                    let exporter = exporter.clone();
+                    let context = context.clone();
+                    async move {
+                        if let Err(e) = exporter.push(&context).await {
+                            log::warn!("Failed to push metrics to {}: {}", exporter.config().addr, e);
+                        }
+                    }
+                });
+            },
+            Err(e) => log::warn!("Metrics will not be pushed to statsd: {}", e),
+        }
+    }
+
+    let jobs = scheduler.start(context.shutdown.clone());
+    let server = Server::new(address)
+        .with_config(config::listener_config(env))
+        .with_context(context.clone());
+
+    #[cfg(feature = "sni")]
+    let server = match config::sni_router(env) {
+        Some(router) => server.with_sni_router(router),
+        None => server,
+    };
+
+    config::warn_unsupported(env);
+
+    tokio::spawn(shutdown_on_ctrl_c(context.clone()));
+    let result = tokio::select! {
+        res = tokio::spawn(run_console(context)) => res.map_err(|e| Error::Task("console", e)),
+        res = tokio::spawn(server.run()) => res.map_err(|e| Error::Task("server", e))?,
+    };
+
+    jobs.shutdown().await;
+    result
+}
@@ -0,0 +1,192 @@
+use std::{collections::BTreeSet, fmt::Display, sync::Arc};
+
+use crate::{middleware::{Middleware, Next}, models::{HttpRequest, HttpResponse, HttpStatusCode}};
+
+type PolicyFn = Arc<dyn Fn(&AuthContext, &HttpRequest) -> bool + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AuthMethod {
+    Basic,
+    Bearer,
+    ApiKey,
+    MutualTls,
+    Session,
+    Custom(&'static str),
+}
+
+impl Display for AuthMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Basic => write!(f, "basic"),
+            Self::Bearer => write!(f, "bearer"),
+            Self::ApiKey => write!(f, "api-key"),
+            Self::MutualTls => write!(f, "mtls"),
+            Self::Session => write!(f, "session"),
+            Self::Custom(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthContext {
+    identity: String,
+    method: AuthMethod,
+    scopes: BTreeSet<String>,
+    roles: BTreeSet<String>,
+}
+
+impl AuthContext {
+    pub fn new(identity: impl Display, method: AuthMethod) -> Self {
+        Self { identity: identity.to_string(), method, scopes: BTreeSet::new(), roles: BTreeSet::new() }
+    }
+
+    pub fn with_scopes<I, S>(mut self, scopes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Display,
+    {
+        self.scopes.extend(scopes.into_iter().map(|scope| scope.to_string()));
+        self
+    }
+
+    pub fn with_roles<I, S>(mut self, roles: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Display,
+    {
+        self.roles.extend(roles.into_iter().map(|role| role.to_string()));
+        self
+    }
+
+    pub fn from_request(request: &HttpRequest) -> Option<&Self> {
+        request.extensions().get()
+    }
+
+    pub fn attach(self, request: &mut HttpRequest) -> Option<Self> {
+        request.extensions_mut().insert(self)
+    }
+
+    pub fn identity(&self) -> &str {
+        &self.identity
+    }
+
+    pub fn method(&self) -> AuthMethod {
+        self.method
+    }
+
+    pub fn scopes(&self) -> impl Iterator<Item = &str> {
+        self.scopes.iter().map(|scope| scope.as_str())
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.contains(scope)
+    }
+
+    pub fn has_all_scopes<'a>(&self, scopes: impl IntoIterator<Item = &'a str>) -> bool {
+        scopes.into_iter().all(|scope| self.has_scope(scope))
+    }
+
+    pub fn roles(&self) -> impl Iterator<Item = &str> {
+        self.roles.iter().map(|role| role.as_str())
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.contains(role)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct AccessPolicy {
+    scopes: BTreeSet<String>,
+    any_roles: BTreeSet<String>,
+    check: Option<PolicyFn>,
+    challenge: Option<String>,
+}
+
+impl AccessPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn require_scope(mut self, scope: impl Display) -> Self {
+        self.scopes.insert(scope.to_string());
+        self
+    }
+
+    pub fn require_any_role<I, S>(mut self, roles: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Display,
+    {
+        self.any_roles.extend(roles.into_iter().map(|role| role.to_string()));
+        self
+    }
+
+    pub fn with_challenge(mut self, challenge: impl Display) -> Self {
+        self.challenge = Some(challenge.to_string());
+        self
+    }
+
+    pub fn with_check<F>(mut self, check: F) -> Self
+    where
+        F: Fn(&AuthContext, &HttpRequest) -> bool + Send + Sync + 'static,
+    {
+        self.check = Some(Arc::new(check));
+        self
+    }
+
+    pub fn authorize(&self, request: &HttpRequest) -> Result<(), HttpStatusCode> {
+        // A 401 must carry a WWW-Authenticate challenge, so without one there is nothing the client can retry with.
+        let unauthenticated = match self.challenge {
+            Some(_) => HttpStatusCode::Unauthorized,
+            None => HttpStatusCode::Forbidden,
+        };
+        let auth = AuthContext::from_request(request).ok_or(unauthenticated)?;
+
+        if !auth.has_all_scopes(self.scopes.iter().map(|scope| scope.as_str())) {
+            return Err(HttpStatusCode::Forbidden);
+        }
+
+        if !self.any_roles.is_empty() && !self.any_roles.iter().any(|role| auth.has_role(role)) {
+            return Err(HttpStatusCode::Forbidden);
+        }
+
+        match &self.check {
+            Some(check) if !check(auth, request) => Err(HttpStatusCode::Forbidden),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl std::fmt::Debug for AccessPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AccessPolicy")
+            .field("scopes", &self.scopes)
+            .field("any_roles", &self.any_roles)
+            .field("check", &self.check.is_some())
+            .field("challenge", &self.challenge)
+            .finish()
+    }
+}
+
+impl Middleware for AccessPolicy {
+    async fn handle(&self, request: &mut HttpRequest, next: Next<'_>) -> HttpResponse {
+        match self.check_head(request) {
+            Some(response) => response,
+            None => next.run(request).await,
+        }
+    }
+
+    fn check_head(&self, request: &mut HttpRequest) -> Option<HttpResponse> {
+        let status = self.authorize(request).err()?;
+        let response = match (status, &self.challenge) {
+            (HttpStatusCode::Unauthorized, Some(challenge)) => HttpResponse::builder()
+                .status(status)
+                .header("WWW-Authenticate", challenge)
+                .body(status.get_readable_name()),
+            _ => HttpResponse::new(status, status.get_readable_name()),
+        };
+
+        Some(response)
+    }
+}
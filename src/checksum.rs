@@ -0,0 +1,210 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use err_derive::Error;
+use md5::Md5;
+use sha2::{Digest, Sha256, Sha512};
+use tokio::io::AsyncReadExt;
+
+use crate::{middleware::{Middleware, Next}, models::{HttpRequest, HttpResponse, HttpStatusCode}};
+
+const READ_CHUNK_SIZE: usize = 16 * 1024;
+const STRUCTURED_DIGEST_HEADERS: [&str; 2] = ["Repr-Digest", "Content-Digest"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DigestAlgorithm {
+    Md5,
+    Sha256,
+    Sha512,
+}
+
+impl DigestAlgorithm {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Md5 => "md5",
+            Self::Sha256 => "sha-256",
+            Self::Sha512 => "sha-512",
+        }
+    }
+
+    pub fn digest(self, bytes: &[u8]) -> Vec<u8> {
+        let mut hasher = Hasher::new(self);
+        hasher.update(bytes);
+        hasher.finish()
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "md5" => Some(Self::Md5),
+            "sha-256" => Some(Self::Sha256),
+            "sha-512" => Some(Self::Sha512),
+            _ => None,
+        }
+    }
+}
+
+impl std::str::FromStr for DigestAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_name(s).ok_or_else(|| format!("'{}' is not a supported digest algorithm", s))
+    }
+}
+
+impl std::fmt::Display for DigestAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ChecksumError {
+    #[error(display = "'{}' is not a valid {} header", _1, _0)]
+    Malformed(&'static str, String),
+    #[error(display = "The body does not match its {} {} digest", _0, _1)]
+    Mismatch(&'static str, DigestAlgorithm),
+    #[error(display = "Failed to read the body: {}", _0)]
+    Io(#[source] std::io::Error),
+}
+
+enum Hasher {
+    Md5(Md5),
+    Sha256(Sha256),
+    Sha512(Sha512),
+}
+
+impl Hasher {
+    fn new(algorithm: DigestAlgorithm) -> Self {
+        match algorithm {
+            DigestAlgorithm::Md5 => Self::Md5(Md5::new()),
+            DigestAlgorithm::Sha256 => Self::Sha256(Sha256::new()),
+            DigestAlgorithm::Sha512 => Self::Sha512(Sha512::new()),
+        }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Self::Md5(hasher) => hasher.update(bytes),
+            Self::Sha256(hasher) => hasher.update(bytes),
+            Self::Sha512(hasher) => hasher.update(bytes),
+        }
+    }
+
+    fn finish(self) -> Vec<u8> {
+        match self {
+            Self::Md5(hasher) => hasher.finalize().to_vec(),
+            Self::Sha256(hasher) => hasher.finalize().to_vec(),
+            Self::Sha512(hasher) => hasher.finalize().to_vec(),
+        }
+    }
+}
+
+struct ExpectedDigest {
+    header: &'static str,
+    algorithm: DigestAlgorithm,
+    value: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BodyChecksum {
+    response_digest: Option<DigestAlgorithm>,
+}
+
+impl BodyChecksum {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_response_digest(mut self, algorithm: DigestAlgorithm) -> Self {
+        self.response_digest = Some(algorithm);
+        self
+    }
+
+    pub async fn verify(request: &HttpRequest) -> Result<(), ChecksumError> {
+        let expected = expected_digests(request)?;
+        if expected.is_empty() {
+            return Ok(());
+        }
+
+        let mut hashers = expected.iter().map(|digest| Hasher::new(digest.algorithm)).collect::<Vec<_>>();
+        let mut reader = request.body().reader().await?;
+        let mut buffer = vec![0_u8; READ_CHUNK_SIZE];
+        loop {
+            match reader.read(&mut buffer).await? {
+                0 => break,
+                count => hashers.iter_mut().for_each(|hasher| hasher.update(&buffer[..count])),
+            }
+        }
+
+        for (digest, hasher) in expected.into_iter().zip(hashers) {
+            if hasher.finish() != digest.value {
+                return Err(ChecksumError::Mismatch(digest.header, digest.algorithm));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Middleware for BodyChecksum {
+    async fn handle(&self, request: &mut HttpRequest, next: Next<'_>) -> HttpResponse {
+        if let Err(e) = Self::verify(request).await {
+            log::warn!("Rejected a request to {}: {}", request.route().path(), e);
+            return match e {
+                ChecksumError::Io(_) => HttpResponse::new(HttpStatusCode::InternalServerError, HttpStatusCode::InternalServerError.get_readable_name()),
+                e => HttpResponse::new(HttpStatusCode::BadRequest, e.to_string()),
+            };
+        }
+
+        let mut response = next.run(request).await;
+        let Some(algorithm) = self.response_digest else {
+            return response;
+        };
+
+        if !response.headers().contains_key("Transfer-Encoding") && !response.headers().contains_key("Repr-Digest") {
+            let digest = STANDARD.encode(algorithm.digest(response.body()));
+            response.headers_mut().insert("Repr-Digest", format!("{}=:{}:", algorithm, digest));
+        }
+
+        response
+    }
+}
+
+fn expected_digests(request: &HttpRequest) -> Result<Vec<ExpectedDigest>, ChecksumError> {
+    let headers = request.headers();
+    let mut expected = Vec::new();
+
+    if let Some(value) = headers.get("Content-MD5") {
+        let value = decode("Content-MD5", value, value.trim())?;
+        expected.push(ExpectedDigest { header: "Content-MD5", algorithm: DigestAlgorithm::Md5, value });
+    }
+
+    if let Some(value) = headers.get("Digest") {
+        for item in value.split(',').filter(|item| !item.trim().is_empty()) {
+            let (name, digest) = item.split_once('=').ok_or_else(|| ChecksumError::Malformed("Digest", value.to_string()))?;
+            if let Some(algorithm) = DigestAlgorithm::from_name(name) {
+                expected.push(ExpectedDigest { header: "Digest", algorithm, value: decode("Digest", value, digest.trim())? });
+            }
+        }
+    }
+
+    for header in STRUCTURED_DIGEST_HEADERS {
+        let Some(value) = headers.get(header) else {
+            continue;
+        };
+
+        for item in value.split(',').filter(|item| !item.trim().is_empty()) {
+            let (name, digest) = item.split_once('=')
+                .and_then(|(name, digest)| Some((name, digest.trim().strip_prefix(':')?.strip_suffix(':')?)))
+                .ok_or_else(|| ChecksumError::Malformed(header, value.to_string()))?;
+
+            if let Some(algorithm) = DigestAlgorithm::from_name(name) {
+                expected.push(ExpectedDigest { header, algorithm, value: decode(header, value, digest)? });
+            }
+        }
+    }
+
+    Ok(expected)
+}
+
+fn decode(header: &'static str, value: &str, digest: &str) -> Result<Vec<u8>, ChecksumError> {
+    STANDARD.decode(digest).map_err(|_| ChecksumError::Malformed(header, value.to_string()))
+}
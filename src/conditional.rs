@@ -0,0 +1,367 @@
+use std::{fmt::Display, time::{Duration, SystemTime, UNIX_EPOCH}};
+
+use crate::models::{HttpMethod, HttpRequest, HttpResponse, HttpStatusCode};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EntityTag {
+    weak: bool,
+    tag: String,
+}
+
+impl EntityTag {
+    pub fn strong(tag: impl Display) -> Self {
+        Self { weak: false, tag: tag.to_string() }
+    }
+
+    pub fn weak(tag: impl Display) -> Self {
+        Self { weak: true, tag: tag.to_string() }
+    }
+
+    pub fn is_weak(&self) -> bool {
+        self.weak
+    }
+
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    pub fn strong_eq(&self, other: &Self) -> bool {
+        !self.weak && !other.weak && self.tag == other.tag
+    }
+
+    pub fn weak_eq(&self, other: &Self) -> bool {
+        self.tag == other.tag
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let (weak, quoted) = match value.strip_prefix("W/") {
+            Some(quoted) => (true, quoted),
+            None => (false, value),
+        };
+
+        let tag = quoted.strip_prefix('"')?.strip_suffix('"')?;
+        (!tag.contains('"')).then(|| Self { weak, tag: tag.to_string() })
+    }
+}
+
+impl Display for EntityTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.weak {
+            true => write!(f, "W/\"{}\"", self.tag),
+            false => write!(f, "\"{}\"", self.tag),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Validators {
+    pub etag: Option<EntityTag>,
+    pub last_modified: Option<SystemTime>,
+}
+
+impl Validators {
+    pub fn new(etag: Option<EntityTag>, last_modified: Option<SystemTime>) -> Self {
+        Self { etag, last_modified: last_modified.map(truncate_to_seconds) }
+    }
+
+    pub fn apply(&self, response: &mut HttpResponse) {
+        if let Some(etag) = &self.etag {
+            response.headers_mut().insert("ETag", etag);
+        }
+
+        if let Some(last_modified) = self.last_modified {
+            response.headers_mut().insert("Last-Modified", httpdate::fmt_http_date(last_modified));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Precondition {
+    Proceed,
+    NotModified,
+    Failed,
+}
+
+impl Precondition {
+    pub fn response(self, validators: &Validators) -> Option<HttpResponse> {
+        let status = match self {
+            Self::Proceed => return None,
+            Self::NotModified => HttpStatusCode::NotModified,
+            Self::Failed => HttpStatusCode::PreconditionFailed,
+        };
+
+        let mut response = HttpResponse::builder().status(status).build();
+        if self == Self::NotModified {
+            validators.apply(&mut response);
+        }
+
+        Some(response)
+    }
+}
+
+// RFC 9110 section 13.2.2
+pub fn evaluate(request: &HttpRequest, validators: &Validators) -> Precondition {
+    let headers = request.headers();
+    let safe = matches!(request.method(), HttpMethod::GET | HttpMethod::HEAD);
+
+    match (headers.get_combined("If-Match"), headers.get("If-Unmodified-Since").and_then(parse_date)) {
+        (Some(condition), _) if !matches_any(&condition, validators, EntityTag::strong_eq) => return Precondition::Failed,
+        (None, Some(date)) if validators.last_modified.is_some_and(|modified| modified > date) => return Precondition::Failed,
+        _ => {},
+    }
+
+    match (headers.get_combined("If-None-Match"), headers.get("If-Modified-Since").and_then(parse_date)) {
+        (Some(condition), _) if matches_any(&condition, validators, EntityTag::weak_eq) => match safe {
+            true => Precondition::NotModified,
+            false => Precondition::Failed,
+        },
+        (None, Some(date)) if safe && validators.last_modified.is_some_and(|modified| modified <= date) => Precondition::NotModified,
+        _ => Precondition::Proceed,
+    }
+}
+
+pub fn range_applies(request: &HttpRequest, validators: &Validators) -> bool {
+    if request.method() != HttpMethod::GET || !request.headers().contains_key("Range") {
+        return false;
+    }
+
+    let Some(condition) = request.headers().get("If-Range") else {
+        return true;
+    };
+
+    match (EntityTag::parse(condition), parse_date(condition)) {
+        (Some(etag), _) => validators.etag.as_ref().is_some_and(|current| current.strong_eq(&etag)),
+        (None, Some(date)) => validators.last_modified.is_some_and(|modified| modified == date),
+        (None, None) => false,
+    }
+}
+
+fn matches_any(condition: &str, validators: &Validators, eq: fn(&EntityTag, &EntityTag) -> bool) -> bool {
+    if condition.trim() == "*" {
+        return true;
+    }
+
+    let Some(current) = &validators.etag else {
+        return false;
+    };
+
+    split_tags(condition).filter_map(EntityTag::parse).any(|etag| eq(current, &etag))
+}
+
+fn split_tags(condition: &str) -> impl Iterator<Item = &str> {
+    let mut rest = condition;
+    std::iter::from_fn(move || {
+        let start = rest.find(|c: char| c != ',' && !c.is_whitespace())?;
+        rest = &rest[start..];
+        let open = rest.find('"')?;
+        let close = open + 1 + rest[open + 1..].find('"')?;
+        let (tag, remaining) = rest.split_at(close + 1);
+        rest = remaining;
+        Some(tag)
+    })
+}
+
+fn parse_date(value: &str) -> Option<SystemTime> {
+    httpdate::parse_http_date(value.trim()).ok()
+}
+
+fn truncate_to_seconds(time: SystemTime) -> SystemTime {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(elapsed) => UNIX_EPOCH + Duration::from_secs(elapsed.as_secs()),
+        Err(_) => time,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use super::*;
+    use crate::models::RequestParser;
+
+    const MODIFIED: u64 = 1_700_000_000;
+
+    fn request(method: &str, headers: &[(&str, &str)]) -> HttpRequest {
+        let mut head = format!("{} /file HTTP/1.1\r\nHost: localhost\r\n", method);
+        for (key, val) in headers {
+            head.push_str(&format!("{}: {}\r\n", key, val));
+        }
+
+        head.push_str("\r\n");
+        RequestParser::default().parse_request(head.as_bytes()).expect("Test request should parse")
+    }
+
+    fn time(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    fn date(secs: u64) -> String {
+        httpdate::fmt_http_date(time(secs))
+    }
+
+    fn validators() -> Validators {
+        Validators::new(Some(EntityTag::strong("abc")), Some(time(MODIFIED)))
+    }
+
+    fn evaluate_with(method: &str, headers: &[(&str, &str)]) -> Precondition {
+        evaluate(&request(method, headers), &validators())
+    }
+
+    #[test]
+    fn entity_tags_parse_strong_and_weak_forms() {
+        assert_eq!(EntityTag::parse("\"abc\""), Some(EntityTag::strong("abc")));
+        assert_eq!(EntityTag::parse(" W/\"abc\" "), Some(EntityTag::weak("abc")));
+        assert_eq!(EntityTag::parse("abc"), None);
+        assert_eq!(EntityTag::parse("\"a\"bc\""), None);
+        assert_eq!(EntityTag::weak("abc").to_string(), "W/\"abc\"");
+    }
+
+    #[test]
+    fn entity_tag_comparisons() {
+        let strong = EntityTag::strong("abc");
+        let weak = EntityTag::weak("abc");
+        assert!(strong.strong_eq(&EntityTag::strong("abc")));
+        assert!(!strong.strong_eq(&weak));
+        assert!(!weak.strong_eq(&weak));
+        assert!(strong.weak_eq(&weak));
+        assert!(weak.weak_eq(&weak));
+        assert!(!strong.weak_eq(&EntityTag::strong("abd")));
+    }
+
+    #[test]
+    fn no_conditions_proceed() {
+        assert_eq!(evaluate_with("GET", &[]), Precondition::Proceed);
+    }
+
+    #[test]
+    fn if_match_uses_strong_comparison() {
+        assert_eq!(evaluate_with("PUT", &[("If-Match", "\"abc\"")]), Precondition::Proceed);
+        assert_eq!(evaluate_with("PUT", &[("If-Match", "W/\"abc\"")]), Precondition::Failed);
+        assert_eq!(evaluate_with("PUT", &[("If-Match", "\"other\"")]), Precondition::Failed);
+        assert_eq!(evaluate_with("PUT", &[("If-Match", "\"other\", \"abc\"")]), Precondition::Proceed);
+    }
+
+    #[test]
+    fn if_match_star_matches_any_current_representation() {
+        assert_eq!(evaluate_with("PUT", &[("If-Match", "*")]), Precondition::Proceed);
+    }
+
+    #[test]
+    fn if_match_without_an_etag_fails() {
+        let validators = Validators::new(None, Some(time(MODIFIED)));
+        assert_eq!(evaluate(&request("PUT", &[("If-Match", "\"abc\"")]), &validators), Precondition::Failed);
+    }
+
+    #[test]
+    fn repeated_if_match_fields_are_combined() {
+        let headers = [("If-Match", "\"other\""), ("If-Match", "\"abc\"")];
+        assert_eq!(evaluate_with("PUT", &headers), Precondition::Proceed);
+    }
+
+    #[test]
+    fn if_match_takes_precedence_over_if_unmodified_since() {
+        let headers = [("If-Match", "\"abc\""), ("If-Unmodified-Since", &date(MODIFIED - 60))];
+        assert_eq!(evaluate_with("PUT", &headers), Precondition::Proceed);
+    }
+
+    #[test]
+    fn if_unmodified_since_fails_when_modified_later() {
+        assert_eq!(evaluate_with("PUT", &[("If-Unmodified-Since", &date(MODIFIED - 60))]), Precondition::Failed);
+        assert_eq!(evaluate_with("PUT", &[("If-Unmodified-Since", &date(MODIFIED))]), Precondition::Proceed);
+        assert_eq!(evaluate_with("PUT", &[("If-Unmodified-Since", &date(MODIFIED + 60))]), Precondition::Proceed);
+    }
+
+    #[test]
+    fn if_none_match_uses_weak_comparison() {
+        assert_eq!(evaluate_with("GET", &[("If-None-Match", "W/\"abc\"")]), Precondition::NotModified);
+        assert_eq!(evaluate_with("GET", &[("If-None-Match", "\"abc\"")]), Precondition::NotModified);
+        assert_eq!(evaluate_with("GET", &[("If-None-Match", "\"other\"")]), Precondition::Proceed);
+    }
+
+    #[test]
+    fn if_none_match_fails_unsafe_methods() {
+        assert_eq!(evaluate_with("HEAD", &[("If-None-Match", "\"abc\"")]), Precondition::NotModified);
+        assert_eq!(evaluate_with("PUT", &[("If-None-Match", "\"abc\"")]), Precondition::Failed);
+        assert_eq!(evaluate_with("PUT", &[("If-None-Match", "*")]), Precondition::Failed);
+    }
+
+    #[test]
+    fn if_none_match_star_matches_any_current_representation() {
+        assert_eq!(evaluate_with("GET", &[("If-None-Match", "*")]), Precondition::NotModified);
+    }
+
+    #[test]
+    fn repeated_if_none_match_fields_are_combined() {
+        let headers = [("If-None-Match", "\"other\""), ("If-None-Match", "W/\"abc\"")];
+        assert_eq!(evaluate_with("GET", &headers), Precondition::NotModified);
+    }
+
+    #[test]
+    fn if_none_match_takes_precedence_over_if_modified_since() {
+        let headers = [("If-None-Match", "\"other\""), ("If-Modified-Since", &date(MODIFIED + 60))];
+        assert_eq!(evaluate_with("GET", &headers), Precondition::Proceed);
+    }
+
+    #[test]
+    fn if_modified_since_only_applies_to_safe_methods() {
+        assert_eq!(evaluate_with("GET", &[("If-Modified-Since", &date(MODIFIED))]), Precondition::NotModified);
+        assert_eq!(evaluate_with("GET", &[("If-Modified-Since", &date(MODIFIED - 60))]), Precondition::Proceed);
+        assert_eq!(evaluate_with("POST", &[("If-Modified-Since", &date(MODIFIED))]), Precondition::Proceed);
+    }
+
+    #[test]
+    fn if_match_failure_takes_precedence_over_if_none_match() {
+        let headers = [("If-Match", "\"other\""), ("If-None-Match", "\"abc\"")];
+        assert_eq!(evaluate_with("GET", &headers), Precondition::Failed);
+    }
+
+    #[test]
+    fn unparsable_dates_are_ignored() {
+        assert_eq!(evaluate_with("PUT", &[("If-Unmodified-Since", "yesterday")]), Precondition::Proceed);
+        assert_eq!(evaluate_with("GET", &[("If-Modified-Since", "not a date")]), Precondition::Proceed);
+    }
+
+    #[test]
+    fn last_modified_is_truncated_to_seconds() {
+        let validators = Validators::new(None, Some(time(MODIFIED) + Duration::from_millis(500)));
+        let request = request("GET", &[("If-Modified-Since", &date(MODIFIED))]);
+        assert_eq!(evaluate(&request, &validators), Precondition::NotModified);
+    }
+
+    #[test]
+    fn not_modified_responses_carry_validators() {
+        let response = Precondition::NotModified.response(&validators()).expect("304 response");
+        assert_eq!(response.status(), HttpStatusCode::NotModified);
+        assert_eq!(response.headers().get("ETag"), Some("\"abc\""));
+        assert!(response.headers().contains_key("Last-Modified"));
+
+        let response = Precondition::Failed.response(&validators()).expect("412 response");
+        assert_eq!(response.status(), HttpStatusCode::PreconditionFailed);
+        assert!(!response.headers().contains_key("ETag"));
+        assert!(Precondition::Proceed.response(&validators()).is_none());
+    }
+
+    #[test]
+    fn range_only_applies_to_get_with_a_range() {
+        assert!(range_applies(&request("GET", &[("Range", "bytes=0-1")]), &validators()));
+        assert!(!range_applies(&request("GET", &[]), &validators()));
+        assert!(!range_applies(&request("HEAD", &[("Range", "bytes=0-1")]), &validators()));
+    }
+
+    #[test]
+    fn if_range_requires_a_strong_etag_match() {
+        let applies = |condition: &str| range_applies(&request("GET", &[("Range", "bytes=0-1"), ("If-Range", condition)]), &validators());
+        assert!(applies("\"abc\""));
+        assert!(!applies("W/\"abc\""));
+        assert!(!applies("\"other\""));
+    }
+
+    #[test]
+    fn if_range_requires_an_exact_date_match() {
+        let applies = |condition: &str| range_applies(&request("GET", &[("Range", "bytes=0-1"), ("If-Range", condition)]), &validators());
+        assert!(applies(&date(MODIFIED)));
+        assert!(!applies(&date(MODIFIED + 60)));
+        assert!(!applies("not a date"));
+    }
+}
@@ -0,0 +1,843 @@
+use std::{collections::HashMap, path::{Path, PathBuf}, sync::Arc, time::Duration};
+
+use log::LevelFilter;
+use tokio_util::sync::CancellationToken;
+
+#[cfg(feature = "body-checksum")]
+use crate::checksum::BodyChecksum;
+#[cfg(feature = "geoip")]
+use crate::geoip::{GeoIp, MmdbReader, RegionPolicy};
+#[cfg(feature = "sni")]
+use crate::sni::SniRouter;
+use crate::{
+    access_log::{AccessLogLevel, AccessLogRules},
+    admission::{AdmissionConfig, AdmissionControl},
+    error_log::ErrorLog,
+    faults::FaultInjector,
+    hooks::ResponseHooks,
+    memory::MemoryAccount,
+    models::{EncodedPathPolicy, HttpMethod, HttpResponse, MethodOverride, ParserProfile, RequestParser},
+    penalty::{PenaltyBox, PenaltyConfig},
+    redact::Redactor,
+    router::{Priority, Router},
+    sampling::{RequestSampler, SampleRate},
+    server::{ListenerConfig, ServerContext, DEFAULT_BODY_SPILL_THRESHOLD, DEFAULT_BODY_TIMEOUT, DEFAULT_HEADER_TIMEOUT, DEFAULT_KEEP_ALIVE_TIMEOUT, DEFAULT_MAX_BODY_SIZE, DEFAULT_MAX_HEAD_SIZE, DEFAULT_SHUTDOWN_TIMEOUT},
+    static_files::static_files,
+    statsd::StatsdConfig,
+    stats::ListenerStats,
+    trace::{RequestTracer, TraceConfig},
+};
+
+const HOST_ADDR_VARIABLE: &str = "HOST_ADDR";
+const LOG_LEVEL_VARIABLE: &str = "LOG_LEVEL";
+const PARSER_PROFILE_VARIABLE: &str = "PARSER_PROFILE";
+const METHOD_OVERRIDE_VARIABLE: &str = "METHOD_OVERRIDE";
+const HEADER_TIMEOUT_VARIABLE: &str = "HEADER_TIMEOUT_MS";
+const BODY_TIMEOUT_VARIABLE: &str = "BODY_TIMEOUT_MS";
+const MAX_HEAD_SIZE_VARIABLE: &str = "MAX_HEAD_SIZE";
+const MAX_BODY_SIZE_VARIABLE: &str = "MAX_BODY_SIZE";
+const KEEP_ALIVE_TIMEOUT_VARIABLE: &str = "KEEP_ALIVE_TIMEOUT_MS";
+const MAX_REQUESTS_PER_CONNECTION_VARIABLE: &str = "MAX_REQUESTS_PER_CONNECTION";
+const SHUTDOWN_TIMEOUT_VARIABLE: &str = "SHUTDOWN_TIMEOUT_MS";
+const LEGACY_CLIENTS_VARIABLE: &str = "LEGACY_CLIENTS";
+const ENCODED_PATH_POLICY_VARIABLE: &str = "ENCODED_PATH_POLICY";
+const READ_BANDWIDTH_VARIABLE: &str = "READ_BANDWIDTH_LIMIT";
+const WRITE_BANDWIDTH_VARIABLE: &str = "WRITE_BANDWIDTH_LIMIT";
+const BODY_SPILL_THRESHOLD_VARIABLE: &str = "BODY_SPILL_THRESHOLD";
+const BODY_MEMORY_CAP_VARIABLE: &str = "BODY_MEMORY_CAP";
+const SLOW_REQUEST_THRESHOLD_VARIABLE: &str = "SLOW_REQUEST_THRESHOLD_MS";
+const BAD_REQUEST_BODY_VARIABLE: &str = "BAD_REQUEST_BODY";
+const PARSE_ERROR_DETAILS_VARIABLE: &str = "LOG_PARSE_ERROR_DETAILS";
+const REDACT_HEADERS_VARIABLE: &str = "REDACT_HEADERS";
+const REDACT_BODY_PATTERN_VARIABLE: &str = "REDACT_BODY_PATTERN";
+const RESPONSE_HEADERS_VARIABLE: &str = "RESPONSE_HEADERS";
+const PENALTY_THRESHOLD_VARIABLE: &str = "PENALTY_THRESHOLD";
+const PENALTY_WINDOW_VARIABLE: &str = "PENALTY_WINDOW_SECS";
+const PENALTY_DURATION_VARIABLE: &str = "PENALTY_DURATION_SECS";
+const SAMPLE_RATE_VARIABLE: &str = "SAMPLE_RATE";
+const SAMPLE_BUFFER_SIZE_VARIABLE: &str = "SAMPLE_BUFFER_SIZE";
+const ACCESS_LOG_ROUTES_VARIABLE: &str = "ACCESS_LOG_ROUTES";
+const SNI_ROUTES_VARIABLE: &str = "SNI_ROUTES";
+const ERROR_LOG_SIZE_VARIABLE: &str = "ERROR_LOG_SIZE";
+const TRACE_MODE_VARIABLE: &str = "TRACE_REQUESTS";
+const TRACE_BODY_LIMIT_VARIABLE: &str = "TRACE_BODY_LIMIT";
+const MAX_CONCURRENT_REQUESTS_VARIABLE: &str = "MAX_CONCURRENT_REQUESTS";
+const ADMISSION_RESERVED_VARIABLE: &str = "ADMISSION_RESERVED_SLOTS";
+const ADMISSION_QUEUE_DEPTH_VARIABLE: &str = "ADMISSION_QUEUE_DEPTH";
+const ADMISSION_QUEUE_TIMEOUT_VARIABLE: &str = "ADMISSION_QUEUE_TIMEOUT_MS";
+const ADMISSION_POLICY_VARIABLE: &str = "ADMISSION_POLICY";
+const STATIC_ROOT_VARIABLE: &str = "STATIC_ROOT";
+const ROUTE_PRIORITIES_VARIABLE: &str = "ROUTE_PRIORITIES";
+const STATSD_ADDR_VARIABLE: &str = "STATSD_ADDR";
+const STATSD_PREFIX_VARIABLE: &str = "STATSD_PREFIX";
+const STATSD_INTERVAL_VARIABLE: &str = "STATSD_INTERVAL_MS";
+const GEOIP_DATABASES_VARIABLE: &str = "GEOIP_DATABASES";
+#[cfg(feature = "geoip")]
+const GEOIP_ALLOW_VARIABLE: &str = "GEOIP_ALLOW_REGIONS";
+#[cfg(feature = "geoip")]
+const GEOIP_DENY_VARIABLE: &str = "GEOIP_DENY_REGIONS";
+const BODY_CHECKSUMS_VARIABLE: &str = "BODY_CHECKSUMS";
+#[cfg(feature = "body-checksum")]
+const RESPONSE_DIGEST_VARIABLE: &str = "RESPONSE_DIGEST";
+const DEFAULT_SAMPLE_BUFFER_SIZE: usize = 32;
+const DEFAULT_ERROR_LOG_SIZE: usize = 64;
+const DEFAULT_HOST: &str = "127.0.0.1";
+const DEFAULT_PORT: u16 = 8080;
+const DEFAULT_LOG_LEVEL: LevelFilter = LevelFilter::Warn;
+
+// The variables the server is configured from, so config files can be merged in without touching the process environment.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Env {
+    vars: HashMap<String, String>,
+}
+
+impl Env {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_process() -> Self {
+        Self { vars: std::env::vars_os().filter_map(|(key, val)| Some((key.into_string().ok()?, val.into_string().ok()?))).collect() }
+    }
+
+    pub fn with_var(mut self, key: impl Into<String>, val: impl Into<String>) -> Self {
+        self.vars.insert(key.into(), val.into());
+        self
+    }
+
+    pub fn var(&self, key: &str) -> Option<&str> {
+        self.vars.get(key).map(String::as_str)
+    }
+
+    pub fn load_file(&mut self, path: &Path) -> std::io::Result<()> {
+        self.merge(&std::fs::read_to_string(path)?)
+    }
+
+    // Applies KEY=VALUE lines, keeping any variable that is already set.
+    pub fn merge(&mut self, contents: &str) -> std::io::Result<()> {
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=').filter(|(key, _)| !key.trim().is_empty()) else {
+                let message = format!("line {} is not of the form KEY=VALUE", index + 1);
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, message));
+            };
+
+            let value = value.trim();
+            let value = value.strip_prefix('"').and_then(|value| value.strip_suffix('"')).unwrap_or(value);
+            self.vars.entry(key.trim().to_string()).or_insert_with(|| value.to_string());
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Options {
+    pub addr: Option<String>,
+    pub bind: Option<String>,
+    pub port: Option<u16>,
+    pub static_dir: Option<PathBuf>,
+    pub log_level: Option<LevelFilter>,
+}
+
+pub fn parse_log_level(level: &str) -> Result<LevelFilter, String> {
+    level.parse().map_err(|_| format!("'{}' is not a log level", level))
+}
+
+pub fn log_level(env: &Env, options: &Options) -> LevelFilter {
+    if let Some(level) = options.log_level {
+        return level;
+    }
+
+    match env.var(LOG_LEVEL_VARIABLE) {
+        Some(level) => parse_log_level(level).unwrap_or_else(|e| {
+            eprintln!("{}, falling back to {}", e, DEFAULT_LOG_LEVEL);
+            DEFAULT_LOG_LEVEL
+        }),
+        None => DEFAULT_LOG_LEVEL,
+    }
+}
+
+pub fn host_addr(env: &Env, options: &Options) -> String {
+    let addr = options.addr.clone()
+        .or_else(|| env.var(HOST_ADDR_VARIABLE).map(str::to_string))
+        .unwrap_or_else(|| format!("{}:{}", DEFAULT_HOST, DEFAULT_PORT));
+
+    if options.bind.is_none() && options.port.is_none() {
+        return addr;
+    }
+
+    let (host, port) = match addr.rsplit_once(':') {
+        Some((host, port)) if port.parse::<u16>().is_ok() => (host.to_string(), port.to_string()),
+        _ => (addr.clone(), DEFAULT_PORT.to_string()),
+    };
+
+    let host = match &options.bind {
+        Some(bind) if bind.contains(':') && !bind.starts_with('[') => format!("[{}]", bind),
+        Some(bind) => bind.clone(),
+        None => host,
+    };
+
+    format!("{}:{}", host, options.port.map_or(port, |port| port.to_string()))
+}
+
+pub fn listener_config(env: &Env) -> ListenerConfig {
+    let legacy_clients = get_flag(env, LEGACY_CLIENTS_VARIABLE, false);
+    ListenerConfig {
+        parser: RequestParser::new(get_parser_profile(env))
+            .with_encoded_paths(get_encoded_path_policy(env))
+            .with_legacy_clients(legacy_clients),
+        method_override: get_method_override(env),
+        read_limit: get_bandwidth_limit(env, READ_BANDWIDTH_VARIABLE),
+        write_limit: get_bandwidth_limit(env, WRITE_BANDWIDTH_VARIABLE),
+        body_spill_threshold: get_body_spill_threshold(env),
+        slow_request_threshold: get_slow_request_threshold(env),
+        bad_request_body: get_flag(env, BAD_REQUEST_BODY_VARIABLE, true),
+        log_parse_error_details: get_flag(env, PARSE_ERROR_DETAILS_VARIABLE, true),
+        legacy_clients,
+        header_timeout: get_header_timeout(env),
+        body_timeout: get_body_timeout(env),
+        max_head_size: get_max_head_size(env),
+        max_body_size: get_max_body_size(env),
+        keep_alive_timeout: get_keep_alive_timeout(env),
+        max_requests_per_connection: get_max_requests_per_connection(env),
+        shutdown_timeout: get_shutdown_timeout(env),
+    }
+}
+
+pub fn server_context(env: &Env, options: &Options) -> ServerContext {
+    ServerContext {
+        stats: Arc::new(ListenerStats::new()),
+        faults: Arc::new(FaultInjector::default()),
+        redactor: Arc::new(get_redactor(env)),
+        tracer: Arc::new(RequestTracer::new(get_trace_config(env))),
+        penalties: Arc::new(PenaltyBox::new(get_penalty_config(env))),
+        hooks: Arc::new(get_response_hooks(env)),
+        sampler: Arc::new(get_request_sampler(env)),
+        errors: Arc::new(get_error_log(env)),
+        access_log: Arc::new(get_access_log_rules(env)),
+        router: Arc::new(get_router(env, options)),
+        body_memory: Arc::new(MemoryAccount::new("request bodies", get_body_memory_cap(env))),
+        admission: Arc::new(AdmissionControl::new(get_admission_config(env))),
+        #[cfg(feature = "geoip")]
+        geoip: Arc::new(get_geoip(env)),
+        shutdown: CancellationToken::new(),
+    }
+}
+
+pub fn statsd_config(env: &Env) -> Option<StatsdConfig> {
+    get_statsd_config(env)
+}
+
+#[cfg(feature = "sni")]
+pub fn sni_router(env: &Env) -> Option<SniRouter> {
+    get_sni_router(env)
+}
+
+#[cfg_attr(all(feature = "sni", feature = "geoip"), allow(unused_variables))]
+pub fn warn_unsupported(env: &Env) {
+    #[cfg(not(feature = "sni"))]
+    if env.var(SNI_ROUTES_VARIABLE).is_some() {
+        log::warn!("{} is ignored because the server was built without the 'sni' feature", SNI_ROUTES_VARIABLE);
+    }
+
+    #[cfg(not(feature = "geoip"))]
+    if env.var(GEOIP_DATABASES_VARIABLE).is_some() {
+        log::warn!("{} is ignored because the server was built without the 'geoip' feature", GEOIP_DATABASES_VARIABLE);
+    }
+}
+
+fn get_parser_profile(env: &Env) -> ParserProfile {
+    match env.var(PARSER_PROFILE_VARIABLE) {
+        Some(profile) => profile.parse().unwrap_or_else(|e| {
+            log::warn!("{}, falling back to the standard profile", e);
+            ParserProfile::Standard
+        }),
+        None => ParserProfile::Standard,
+    }
+}
+
+fn get_method_override(env: &Env) -> MethodOverride {
+    match env.var(METHOD_OVERRIDE_VARIABLE) {
+        Some(policy) => policy.parse().unwrap_or_else(|e| {
+            log::warn!("{}, method override is disabled", e);
+            MethodOverride::Disabled
+        }),
+        None => MethodOverride::Disabled,
+    }
+}
+
+fn get_encoded_path_policy(env: &Env) -> EncodedPathPolicy {
+    match env.var(ENCODED_PATH_POLICY_VARIABLE) {
+        Some(policy) => policy.parse().unwrap_or_else(|e| {
+            log::warn!("{}, encoded slashes and dots are decoded", e);
+            EncodedPathPolicy::Decode
+        }),
+        None => EncodedPathPolicy::Decode,
+    }
+}
+
+fn get_bandwidth_limit(env: &Env, variable: &str) -> Option<u64> {
+    let limit = env.var(variable)?;
+    match limit.parse() {
+        Ok(0) => None,
+        Ok(limit) => Some(limit),
+        Err(e) => {
+            log::warn!("'{}' is not a valid value for {}: {}", limit, variable, e);
+            None
+        }
+    }
+}
+
+fn get_body_spill_threshold(env: &Env) -> usize {
+    match env.var(BODY_SPILL_THRESHOLD_VARIABLE) {
+        Some(threshold) => threshold.parse().unwrap_or_else(|e| {
+            log::warn!("'{}' is not a valid body spill threshold: {}", threshold, e);
+            DEFAULT_BODY_SPILL_THRESHOLD
+        }),
+        None => DEFAULT_BODY_SPILL_THRESHOLD,
+    }
+}
+
+fn get_header_timeout(env: &Env) -> Duration {
+    match env.var(HEADER_TIMEOUT_VARIABLE) {
+        Some(timeout) => timeout.parse().map(Duration::from_millis).unwrap_or_else(|e| {
+            log::warn!("'{}' is not a valid header timeout: {}", timeout, e);
+            DEFAULT_HEADER_TIMEOUT
+        }),
+        None => DEFAULT_HEADER_TIMEOUT,
+    }
+}
+
+fn get_body_timeout(env: &Env) -> Duration {
+    match env.var(BODY_TIMEOUT_VARIABLE) {
+        Some(timeout) => timeout.parse().map(Duration::from_millis).unwrap_or_else(|e| {
+            log::warn!("'{}' is not a valid body timeout: {}", timeout, e);
+            DEFAULT_BODY_TIMEOUT
+        }),
+        None => DEFAULT_BODY_TIMEOUT,
+    }
+}
+
+fn get_max_head_size(env: &Env) -> usize {
+    match env.var(MAX_HEAD_SIZE_VARIABLE) {
+        Some(size) => size.parse().unwrap_or_else(|e| {
+            log::warn!("'{}' is not a valid head size limit: {}", size, e);
+            DEFAULT_MAX_HEAD_SIZE
+        }),
+        None => DEFAULT_MAX_HEAD_SIZE,
+    }
+}
+
+fn get_max_body_size(env: &Env) -> u64 {
+    match env.var(MAX_BODY_SIZE_VARIABLE) {
+        Some(size) => size.parse().unwrap_or_else(|e| {
+            log::warn!("'{}' is not a valid body size limit: {}", size, e);
+            DEFAULT_MAX_BODY_SIZE
+        }),
+        None => DEFAULT_MAX_BODY_SIZE,
+    }
+}
+
+fn get_keep_alive_timeout(env: &Env) -> Option<Duration> {
+    let timeout = match env.var(KEEP_ALIVE_TIMEOUT_VARIABLE) {
+        Some(timeout) => timeout.parse().map(Duration::from_millis).unwrap_or_else(|e| {
+            log::warn!("'{}' is not a valid keep-alive timeout: {}", timeout, e);
+            DEFAULT_KEEP_ALIVE_TIMEOUT
+        }),
+        None => DEFAULT_KEEP_ALIVE_TIMEOUT,
+    };
+
+    (!timeout.is_zero()).then_some(timeout)
+}
+
+fn get_shutdown_timeout(env: &Env) -> Duration {
+    match env.var(SHUTDOWN_TIMEOUT_VARIABLE) {
+        Some(timeout) => timeout.parse().map(Duration::from_millis).unwrap_or_else(|e| {
+            log::warn!("'{}' is not a valid shutdown timeout: {}", timeout, e);
+            DEFAULT_SHUTDOWN_TIMEOUT
+        }),
+        None => DEFAULT_SHUTDOWN_TIMEOUT,
+    }
+}
+
+fn get_max_requests_per_connection(env: &Env) -> Option<usize> {
+    let max = env.var(MAX_REQUESTS_PER_CONNECTION_VARIABLE)?;
+    match max.parse() {
+        Ok(0) => None,
+        Ok(max) => Some(max),
+        Err(e) => {
+            log::warn!("'{}' is not a valid request limit: {}", max, e);
+            None
+        }
+    }
+}
+
+fn get_body_memory_cap(env: &Env) -> Option<usize> {
+    let cap = env.var(BODY_MEMORY_CAP_VARIABLE)?;
+    match cap.parse() {
+        Ok(cap) => Some(cap),
+        Err(e) => {
+            log::warn!("'{}' is not a valid body memory cap: {}", cap, e);
+            None
+        }
+    }
+}
+
+fn get_slow_request_threshold(env: &Env) -> Option<Duration> {
+    let threshold = env.var(SLOW_REQUEST_THRESHOLD_VARIABLE)?;
+    match threshold.parse() {
+        Ok(millis) => Some(Duration::from_millis(millis)),
+        Err(e) => {
+            log::warn!("'{}' is not a valid slow request threshold: {}", threshold, e);
+            None
+        }
+    }
+}
+
+fn get_admission_config(env: &Env) -> AdmissionConfig {
+    let mut config = AdmissionConfig::default();
+    let parse = |variable: &str| -> Option<u64> {
+        let val = env.var(variable)?;
+        val.parse().map_err(|e| log::warn!("'{}' is not a valid value for {}: {}", val, variable, e)).ok()
+    };
+
+    if let Some(max) = parse(MAX_CONCURRENT_REQUESTS_VARIABLE) {
+        config.max_concurrent = usize::try_from(max).ok().filter(|&max| max > 0);
+    }
+
+    if let Some(reserved) = parse(ADMISSION_RESERVED_VARIABLE) {
+        config.reserved = reserved.try_into().unwrap_or(usize::MAX);
+    }
+
+    if let Some(depth) = parse(ADMISSION_QUEUE_DEPTH_VARIABLE) {
+        config.queue_depth = depth.try_into().unwrap_or(usize::MAX);
+    }
+
+    if let Some(timeout) = parse(ADMISSION_QUEUE_TIMEOUT_VARIABLE) {
+        config.queue_timeout = Duration::from_millis(timeout);
+    }
+
+    if let Some(policy) = env.var(ADMISSION_POLICY_VARIABLE) {
+        config.policy = policy.parse().unwrap_or_else(|e| {
+            log::warn!("{}", e);
+            config.policy
+        });
+    }
+
+    config
+}
+
+fn get_redactor(env: &Env) -> Redactor {
+    let mut redactor = Redactor::new();
+    if let Some(headers) = env.var(REDACT_HEADERS_VARIABLE) {
+        for name in headers.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            redactor = redactor.redact_header(name);
+        }
+    }
+
+    #[cfg(feature = "body-redaction")]
+    if let Some(pattern) = env.var(REDACT_BODY_PATTERN_VARIABLE) {
+        redactor = match redactor.clone().redact_body_pattern(pattern) {
+            Ok(redactor) => redactor,
+            Err(e) => {
+                log::warn!("'{}' is not a valid body redaction pattern: {}", pattern, e);
+                redactor
+            }
+        };
+    }
+
+    #[cfg(not(feature = "body-redaction"))]
+    if env.var(REDACT_BODY_PATTERN_VARIABLE).is_some() {
+        log::warn!("{} is ignored because the server was built without the 'body-redaction' feature", REDACT_BODY_PATTERN_VARIABLE);
+    }
+
+    redactor
+}
+
+fn get_trace_config(env: &Env) -> TraceConfig {
+    let mut config = TraceConfig::default();
+    if let Some(mode) = env.var(TRACE_MODE_VARIABLE) {
+        match mode.parse() {
+            Ok(mode) => config.mode = mode,
+            Err(e) => log::warn!("{}, request tracing stays {}", e, config.mode),
+        }
+    }
+
+    if let Some(limit) = env.var(TRACE_BODY_LIMIT_VARIABLE) {
+        match limit.parse() {
+            Ok(limit) => config.body_limit = limit,
+            Err(e) => log::warn!("'{}' is not a valid trace body limit: {}", limit, e),
+        }
+    }
+
+    config
+}
+
+fn get_penalty_config(env: &Env) -> PenaltyConfig {
+    let mut config = PenaltyConfig::default();
+    let parse = |variable: &str| -> Option<u64> {
+        let val = env.var(variable)?;
+        val.parse().map_err(|e| log::warn!("'{}' is not a valid value for {}: {}", val, variable, e)).ok()
+    };
+
+    if let Some(threshold) = parse(PENALTY_THRESHOLD_VARIABLE) {
+        config.threshold = threshold.try_into().unwrap_or(u32::MAX);
+    }
+
+    if let Some(window) = parse(PENALTY_WINDOW_VARIABLE) {
+        config.window = Duration::from_secs(window);
+    }
+
+    if let Some(duration) = parse(PENALTY_DURATION_VARIABLE) {
+        config.duration = Duration::from_secs(duration);
+    }
+
+    config
+}
+
+fn get_statsd_config(env: &Env) -> Option<StatsdConfig> {
+    let mut config = StatsdConfig::new(env.var(STATSD_ADDR_VARIABLE)?);
+    if let Some(prefix) = env.var(STATSD_PREFIX_VARIABLE) {
+        config.prefix = prefix.to_string();
+    }
+
+    if let Some(interval) = env.var(STATSD_INTERVAL_VARIABLE) {
+        match interval.parse() {
+            Ok(0) => log::warn!("The statsd push interval must be greater than zero"),
+            Ok(millis) => config.interval = Duration::from_millis(millis),
+            Err(e) => log::warn!("'{}' is not a valid statsd push interval: {}", interval, e),
+        }
+    }
+
+    Some(config)
+}
+
+#[cfg(feature = "geoip")]
+fn get_geoip(env: &Env) -> GeoIp {
+    let mut geoip = GeoIp::new();
+    let Some(paths) = env.var(GEOIP_DATABASES_VARIABLE) else {
+        return geoip;
+    };
+
+    for path in paths.split(',').map(str::trim).filter(|path| !path.is_empty()) {
+        match MmdbReader::open(path) {
+            Ok(database) => geoip = geoip.with_database(database),
+            Err(e) => log::warn!("Failed to load GeoIP database '{}': {}", path, e),
+        }
+    }
+
+    geoip
+}
+
+#[cfg(feature = "geoip")]
+fn get_region_policy(env: &Env) -> RegionPolicy {
+    let mut policy = RegionPolicy::new();
+    let regions = |variable: &str| -> Vec<String> {
+        env.var(variable)
+            .map(|regions| regions.split(',').map(str::trim).filter(|region| !region.is_empty()).map(str::to_string).collect())
+            .unwrap_or_default()
+    };
+
+    for region in regions(GEOIP_ALLOW_VARIABLE) {
+        policy = policy.allow(region);
+    }
+
+    for region in regions(GEOIP_DENY_VARIABLE) {
+        policy = policy.deny(region);
+    }
+
+    policy
+}
+
+fn get_response_hooks(env: &Env) -> ResponseHooks {
+    let mut hooks = ResponseHooks::new();
+    let Some(headers) = env.var(RESPONSE_HEADERS_VARIABLE) else {
+        return hooks;
+    };
+
+    let headers: Vec<(String, String)> = headers.split('|')
+        .filter(|header| !header.trim().is_empty())
+        .filter_map(|header| match header.split_once(':') {
+            Some((key, val)) => Some((key.trim().to_string(), val.trim().to_string())),
+            None => {
+                log::warn!("'{}' is not a valid response header", header);
+                None
+            }
+        })
+        .collect();
+
+    if !headers.is_empty() {
+        hooks.add("response headers", move |_, response| {
+            for (key, val) in &headers {
+                response.headers_mut().insert(key, val);
+            }
+        });
+    }
+
+    hooks
+}
+
+fn get_request_sampler(env: &Env) -> RequestSampler {
+    let rate = match env.var(SAMPLE_RATE_VARIABLE) {
+        Some(rate) => rate.parse().unwrap_or_else(|e| {
+            log::warn!("{}, request sampling is off", e);
+            SampleRate::Off
+        }),
+        None => SampleRate::Off,
+    };
+
+    let capacity = match env.var(SAMPLE_BUFFER_SIZE_VARIABLE) {
+        Some(capacity) => capacity.parse().unwrap_or_else(|e| {
+            log::warn!("'{}' is not a valid sample buffer size: {}", capacity, e);
+            DEFAULT_SAMPLE_BUFFER_SIZE
+        }),
+        None => DEFAULT_SAMPLE_BUFFER_SIZE,
+    };
+
+    RequestSampler::new(rate, capacity)
+}
+
+fn get_error_log(env: &Env) -> ErrorLog {
+    match env.var(ERROR_LOG_SIZE_VARIABLE) {
+        Some(size) => ErrorLog::new(size.parse().unwrap_or_else(|e| {
+            log::warn!("'{}' is not a valid error log size: {}", size, e);
+            DEFAULT_ERROR_LOG_SIZE
+        })),
+        None => ErrorLog::new(DEFAULT_ERROR_LOG_SIZE),
+    }
+}
+
+fn get_access_log_rules(env: &Env) -> AccessLogRules {
+    let mut rules = AccessLogRules::new();
+    let Some(routes) = env.var(ACCESS_LOG_ROUTES_VARIABLE) else {
+        return rules;
+    };
+
+    for rule in routes.split(',').map(str::trim).filter(|rule| !rule.is_empty()) {
+        let Some((route, level)) = rule.split_once('=') else {
+            log::warn!("'{}' is not a valid access log rule", rule);
+            continue;
+        };
+
+        match level.trim().parse::<AccessLogLevel>() {
+            Ok(level) => rules = rules.route(route.trim(), level),
+            Err(e) => log::warn!("{}, ignoring the rule for {}", e, route),
+        }
+    }
+
+    rules
+}
+
+#[cfg(feature = "sni")]
+fn get_sni_router(env: &Env) -> Option<SniRouter> {
+    let routes = env.var(SNI_ROUTES_VARIABLE)?;
+    let mut router = SniRouter::new();
+    for route in routes.split(',').map(str::trim).filter(|route| !route.is_empty()) {
+        router = match route.split_once('=') {
+            Some(("*", backend)) => router.fallback(backend.trim()),
+            Some((name, backend)) => match router.clone().route(name.trim(), backend.trim()) {
+                Ok(router) => router,
+                Err(e) => {
+                    log::warn!("{}, ignoring its SNI route", e);
+                    router
+                }
+            },
+            None => {
+                log::warn!("'{}' is not a valid SNI route", route);
+                router
+            }
+        };
+    }
+
+    (!router.is_empty()).then_some(router)
+}
+
+fn get_router(env: &Env, options: &Options) -> Router {
+    let mut router = Router::new();
+    router.get("/", |_| HttpResponse::im_a_teapot("Hello!"));
+
+    #[cfg(feature = "geoip")]
+    {
+        let policy = get_region_policy(env);
+        if !policy.is_empty() {
+            router.middleware(policy);
+        }
+    }
+
+    #[cfg(feature = "body-checksum")]
+    if get_flag(env, BODY_CHECKSUMS_VARIABLE, false) {
+        let mut checksums = BodyChecksum::new();
+        if let Some(algorithm) = env.var(RESPONSE_DIGEST_VARIABLE) {
+            match algorithm.parse() {
+                Ok(algorithm) => checksums = checksums.with_response_digest(algorithm),
+                Err(e) => log::warn!("{}", e),
+            }
+        }
+
+        router.middleware(checksums);
+    }
+
+    #[cfg(not(feature = "body-checksum"))]
+    if env.var(BODY_CHECKSUMS_VARIABLE).is_some() {
+        log::warn!("{} is ignored because the server was built without the 'body-checksum' feature", BODY_CHECKSUMS_VARIABLE);
+    }
+
+    let static_root = options.static_dir.clone().or_else(|| env.var(STATIC_ROOT_VARIABLE).map(PathBuf::from));
+    if let Some(root) = static_root {
+        static_files(&mut router, "/static/*path", root);
+    }
+
+    if let Some(priorities) = env.var(ROUTE_PRIORITIES_VARIABLE) {
+        for rule in priorities.split(',').map(str::trim).filter(|rule| !rule.is_empty()) {
+            let Some((route, priority)) = rule.split_once('=') else {
+                log::warn!("'{}' is not a valid route priority", rule);
+                continue;
+            };
+
+            let Some((method, path)) = route.trim().split_once(' ') else {
+                log::warn!("'{}' is not a valid route, expected a method and a path", route);
+                continue;
+            };
+
+            match (method.parse::<HttpMethod>(), priority.trim().parse::<Priority>()) {
+                (Ok(method), Ok(priority)) => { router.route_priority(method, path.trim(), priority); },
+                (Err(e), _) => log::warn!("{}, ignoring the priority for {}", e, route),
+                (_, Err(e)) => log::warn!("{}, ignoring the priority for {}", e, route),
+            }
+        }
+    }
+
+    router
+}
+
+fn get_flag(env: &Env, variable: &str, default: bool) -> bool {
+    match env.var(variable).map(|val| val.to_ascii_lowercase()) {
+        Some(val) if matches!(val.as_str(), "1" | "true" | "on" | "yes") => true,
+        Some(val) if matches!(val.as_str(), "0" | "false" | "off" | "no") => false,
+        Some(val) => {
+            log::warn!("'{}' is not a valid value for {}", val, variable);
+            default
+        },
+        None => default,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_fills_unset_variables() {
+        let mut env = Env::new().with_var("HOST_ADDR", "0.0.0.0:80");
+        let contents = "# listener\n\nHOST_ADDR=127.0.0.1:9000\n  LOG_LEVEL = debug \nSTATIC_ROOT=\"/srv/www files\"\nEMPTY=\n";
+        env.merge(contents).unwrap();
+
+        assert_eq!(env.var("HOST_ADDR"), Some("0.0.0.0:80"));
+        assert_eq!(env.var("LOG_LEVEL"), Some("debug"));
+        assert_eq!(env.var("STATIC_ROOT"), Some("/srv/www files"));
+        assert_eq!(env.var("EMPTY"), Some(""));
+        assert_eq!(env.var("# listener"), None);
+    }
+
+    #[test]
+    fn merge_rejects_lines_without_a_key() {
+        let mut env = Env::new();
+        let error = env.merge("LOG_LEVEL=info\nnot a setting\n").unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("line 2"));
+
+        assert!(env.merge("=value").is_err());
+    }
+
+    #[test]
+    fn log_level_prefers_the_option() {
+        let env = Env::new().with_var(LOG_LEVEL_VARIABLE, "debug");
+        assert_eq!(log_level(&env, &Options::default()), LevelFilter::Debug);
+
+        let options = Options { log_level: Some(LevelFilter::Error), ..Options::default() };
+        assert_eq!(log_level(&env, &options), LevelFilter::Error);
+
+        let env = Env::new().with_var(LOG_LEVEL_VARIABLE, "loud");
+        assert_eq!(log_level(&env, &Options::default()), DEFAULT_LOG_LEVEL);
+        assert!(parse_log_level("loud").is_err());
+    }
+
+    #[test]
+    fn host_addr_combines_options_and_env() {
+        let env = Env::new();
+        assert_eq!(host_addr(&env, &Options::default()), "127.0.0.1:8080");
+
+        let env = Env::new().with_var(HOST_ADDR_VARIABLE, "0.0.0.0:3000");
+        assert_eq!(host_addr(&env, &Options::default()), "0.0.0.0:3000");
+
+        let options = Options { addr: Some(String::from("localhost:4000")), ..Options::default() };
+        assert_eq!(host_addr(&env, &options), "localhost:4000");
+
+        let options = Options { port: Some(9000), ..Options::default() };
+        assert_eq!(host_addr(&env, &options), "0.0.0.0:9000");
+
+        let options = Options { bind: Some(String::from("::1")), ..Options::default() };
+        assert_eq!(host_addr(&env, &options), "[::1]:3000");
+
+        let env = Env::new().with_var(HOST_ADDR_VARIABLE, "example.com");
+        let options = Options { bind: Some(String::from("[::]")), port: Some(81), ..Options::default() };
+        assert_eq!(host_addr(&env, &options), "[::]:81");
+    }
+
+    #[test]
+    fn listener_config_defaults() {
+        let config = listener_config(&Env::new());
+        assert_eq!(config.parser, RequestParser::new(ParserProfile::Standard));
+        assert_eq!(config.header_timeout, DEFAULT_HEADER_TIMEOUT);
+        assert_eq!(config.max_head_size, DEFAULT_MAX_HEAD_SIZE);
+        assert_eq!(config.keep_alive_timeout, Some(DEFAULT_KEEP_ALIVE_TIMEOUT));
+        assert_eq!(config.read_limit, None);
+        assert!(config.bad_request_body);
+        assert!(!config.legacy_clients);
+    }
+
+    #[test]
+    fn listener_config_reads_env() {
+        let env = Env::new()
+            .with_var(PARSER_PROFILE_VARIABLE, "strict")
+            .with_var(LEGACY_CLIENTS_VARIABLE, "yes")
+            .with_var(HEADER_TIMEOUT_VARIABLE, "250")
+            .with_var(MAX_HEAD_SIZE_VARIABLE, "not a size")
+            .with_var(KEEP_ALIVE_TIMEOUT_VARIABLE, "0")
+            .with_var(READ_BANDWIDTH_VARIABLE, "1024")
+            .with_var(BAD_REQUEST_BODY_VARIABLE, "Off");
+
+        let config = listener_config(&env);
+        assert_eq!(config.parser, RequestParser::new(ParserProfile::Strict).with_legacy_clients(true));
+        assert!(config.legacy_clients);
+        assert_eq!(config.header_timeout, Duration::from_millis(250));
+        assert_eq!(config.max_head_size, DEFAULT_MAX_HEAD_SIZE);
+        assert_eq!(config.keep_alive_timeout, None);
+        assert_eq!(config.read_limit, Some(1024));
+        assert!(!config.bad_request_body);
+    }
+
+    #[test]
+    fn flags() {
+        let env = Env::new().with_var("A", "TRUE").with_var("B", "0").with_var("C", "maybe");
+        assert!(get_flag(&env, "A", false));
+        assert!(!get_flag(&env, "B", true));
+        assert!(get_flag(&env, "C", true));
+        assert!(!get_flag(&env, "D", false));
+    }
+
+    #[test]
+    fn static_dir_option_overrides_env() {
+        let without = get_router(&Env::new(), &Options::default()).len();
+        let env = Env::new().with_var(STATIC_ROOT_VARIABLE, "/srv/www");
+        assert!(get_router(&env, &Options::default()).len() > without);
+
+        let options = Options { static_dir: Some(PathBuf::from("/srv/other")), ..Options::default() };
+        assert!(get_router(&Env::new(), &options).len() > without);
+    }
+}
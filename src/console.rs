@@ -0,0 +1,248 @@
+use std::time::Duration;
+
+#[cfg(feature = "runtime-metrics")]
+use crate::stats::RuntimeStats;
+use crate::{
+    faults::{Delay, FaultInjector},
+    sampling::{RequestSampler, SampleRate},
+    server::ServerContext,
+    trace::{RequestTracer, TraceMode},
+};
+use tokio::sync::mpsc;
+
+fn spawn_stdin_reader() -> mpsc::UnboundedReceiver<String> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    log::error!("Failed to read from the console: {}", e);
+                    break;
+                }
+            };
+
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    receiver
+}
+
+pub async fn shutdown_on_ctrl_c(context: ServerContext) {
+    match tokio::signal::ctrl_c().await {
+        Ok(()) => {
+            println!("Received Ctrl+C, shutting down");
+            context.begin_shutdown();
+        },
+        Err(e) => log::error!("Failed to listen for Ctrl+C: {}", e),
+    }
+}
+
+pub async fn run_console(context: ServerContext) {
+    let mut commands = spawn_stdin_reader();
+
+    while let Some(command) = commands.recv().await {
+        handle_command(&context, &command);
+    }
+
+    std::future::pending::<()>().await
+}
+
+fn handle_command(context: &ServerContext, command: &str) {
+    let parts = command
+        .split_whitespace()
+        .filter(|s| !s.trim().is_empty())
+        .collect::<Vec<_>>();
+
+    match parts.first().copied() {
+        Some("quit" | "q" | "stop") => {
+            println!("Shutting down, {} requests in flight", context.stats.active_requests());
+            context.begin_shutdown();
+        },
+        Some("drain") => {
+            context.stats.start_draining();
+            println!("Listener is draining, {} requests in flight", context.stats.active_requests());
+        },
+        Some("stats") => {
+            println!("{}, penalized clients: {}", context.stats, context.penalties.penalized());
+            println!("memory: {}", context.body_memory);
+            println!("admission: {}", context.admission);
+            #[cfg(feature = "geoip")]
+            if context.geoip.is_enabled() {
+                println!("geoip: {}", context.geoip);
+            }
+            #[cfg(feature = "runtime-metrics")]
+            if let Some(runtime) = RuntimeStats::capture() {
+                println!("runtime: {}", runtime);
+            }
+        },
+        Some("trace") => match handle_trace_command(&context.tracer, &parts[1..]) {
+            Ok(()) => println!("Request tracing {}", context.tracer.config()),
+            Err(e) => println!("{}", e),
+        },
+        Some("samples") => match handle_samples_command(&context.sampler, &parts[1..]) {
+            Ok(()) => println!("Request sampling: {}", context.sampler.rate()),
+            Err(e) => println!("{}", e),
+        },
+        Some("errors") => match parts.get(1).copied() {
+            None => context.errors.records().iter().for_each(|record| println!("{}", record)),
+            Some("clear") => context.errors.clear(),
+            Some(other) => println!("Unknown errors option '{}'", other),
+        },
+        Some("faults") => match handle_faults_command(&context.faults, &parts[1..]) {
+            Ok(()) => println!("Fault injection enabled: {}, {}", context.faults.is_enabled(), context.faults.config()),
+            Err(e) => println!("{}", e),
+        },
+        _ => ()
+    }
+}
+
+fn handle_samples_command(sampler: &RequestSampler, args: &[&str]) -> Result<(), String> {
+    match args.first().copied() {
+        None => {
+            for sample in sampler.samples() {
+                println!("{}\n", sample);
+            }
+        },
+        Some("clear") => sampler.clear(),
+        Some("rate") => {
+            let rate: SampleRate = args.get(1).ok_or("Missing sample rate")?.parse()?;
+            sampler.set_rate(rate);
+        },
+        Some(other) => return Err(format!("Unknown samples option '{}'", other)),
+    }
+
+    Ok(())
+}
+
+fn handle_faults_command(faults: &FaultInjector, args: &[&str]) -> Result<(), String> {
+    let parse_rate = |rate: Option<&&str>| -> Result<f64, String> {
+        rate.ok_or("Missing fault rate")?
+            .parse::<f64>()
+            .ok()
+            .filter(|rate| (0.0..=1.0).contains(rate))
+            .ok_or_else(|| String::from("Fault rates must be between 0 and 1"))
+    };
+
+    let parse_millis = |millis: &str| -> Result<Duration, String> {
+        millis.parse()
+            .map(Duration::from_millis)
+            .map_err(|_| format!("'{}' is not a valid delay in milliseconds", millis))
+    };
+
+    match args.first().copied() {
+        None => (),
+        Some("on") => faults.set_enabled(true),
+        Some("off") => faults.set_enabled(false),
+        Some("delay") => {
+            let delay = match args.get(1).copied() {
+                None | Some("none") => Delay::None,
+                Some(range) => match range.split_once('-') {
+                    Some((min, max)) => Delay::Uniform { min: parse_millis(min)?, max: parse_millis(max)? },
+                    None => Delay::Fixed(parse_millis(range)?),
+                },
+            };
+
+            faults.update(|config| config.delay = delay);
+        },
+        Some("error") => {
+            let rate = parse_rate(args.get(1))?;
+            faults.update(|config| config.error_rate = rate);
+        },
+        Some("reset") => {
+            let rate = parse_rate(args.get(1))?;
+            faults.update(|config| config.reset_rate = rate);
+        },
+        Some("truncate") => {
+            let rate = parse_rate(args.get(1))?;
+            faults.update(|config| config.truncate_rate = rate);
+        },
+        Some(other) => return Err(format!("Unknown faults option '{}'", other)),
+    }
+
+    Ok(())
+}
+
+fn handle_trace_command(tracer: &RequestTracer, args: &[&str]) -> Result<(), String> {
+    match args.first().copied() {
+        None => (),
+        Some("limit") => {
+            let limit = args.get(1)
+                .ok_or("Missing trace body limit")?
+                .parse()
+                .map_err(|_| String::from("The trace body limit must be a number of bytes"))?;
+
+            tracer.update(|config| config.body_limit = limit);
+        },
+        Some(mode) => {
+            let mode: TraceMode = mode.parse()?;
+            tracer.update(|config| config.mode = mode);
+        },
+    }
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn faults_command() {
+        let faults = FaultInjector::default();
+        handle_faults_command(&faults, &["on"]).unwrap();
+        handle_faults_command(&faults, &["delay", "10-20"]).unwrap();
+        handle_faults_command(&faults, &["error", "0.5"]).unwrap();
+
+        assert!(faults.is_enabled());
+        assert_eq!(faults.config().delay, Delay::Uniform { min: Duration::from_millis(10), max: Duration::from_millis(20) });
+        assert_eq!(faults.config().error_rate, 0.5);
+
+        assert!(handle_faults_command(&faults, &["reset", "2"]).is_err());
+        assert!(handle_faults_command(&faults, &["delay", "soon"]).is_err());
+        assert!(handle_faults_command(&faults, &["explode"]).is_err());
+        assert_eq!(faults.config().reset_rate, 0.0);
+
+        handle_faults_command(&faults, &["delay"]).unwrap();
+        assert_eq!(faults.config().delay, Delay::None);
+    }
+
+    #[test]
+    fn trace_command() {
+        let tracer = RequestTracer::default();
+        handle_trace_command(&tracer, &["headers"]).unwrap();
+        handle_trace_command(&tracer, &["limit", "64"]).unwrap();
+
+        assert_eq!(tracer.config().mode, TraceMode::Headers);
+        assert_eq!(tracer.config().body_limit, 64);
+        assert!(handle_trace_command(&tracer, &["limit"]).is_err());
+        assert!(handle_trace_command(&tracer, &["sometimes"]).is_err());
+    }
+
+    #[test]
+    fn samples_command() {
+        let sampler = RequestSampler::default();
+        handle_samples_command(&sampler, &["rate", "5/s"]).unwrap();
+        assert_eq!(sampler.rate(), SampleRate::PerSecond(5));
+
+        assert!(handle_samples_command(&sampler, &["rate"]).is_err());
+        assert!(handle_samples_command(&sampler, &["rate", "often"]).is_err());
+        assert_eq!(sampler.rate(), SampleRate::PerSecond(5));
+    }
+
+    #[test]
+    fn quit_and_drain_commands() {
+        let context = ServerContext::default();
+        handle_command(&context, "  drain ");
+        assert!(context.stats.is_draining());
+        assert!(!context.shutdown.is_cancelled());
+
+        handle_command(&context, "q");
+        assert!(context.shutdown.is_cancelled());
+    }
+}
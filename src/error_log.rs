@@ -0,0 +1,65 @@
+use std::{fmt::Display, net::SocketAddr, time::SystemTime};
+
+use crate::{errors::{ConnectionError, ConnectionErrorKind}, ring::RingBuffer};
+
+#[derive(Debug, Clone)]
+pub struct ErrorRecord {
+    pub occurred_at: SystemTime,
+    pub client: SocketAddr,
+    pub kind: ConnectionErrorKind,
+    pub message: String,
+    pub request: Option<String>,
+}
+
+impl ErrorRecord {
+    pub fn new(client: SocketAddr, error: &ConnectionError, request: Option<String>) -> Self {
+        Self {
+            occurred_at: SystemTime::now(),
+            client,
+            kind: error.kind(),
+            message: error.to_string(),
+            request,
+        }
+    }
+}
+
+impl Display for ErrorRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let since_epoch = self.occurred_at.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+        write!(f, "[{:.3}] {} ({}): {}", since_epoch.as_secs_f64(), self.client, self.kind, self.message)?;
+
+        match &self.request {
+            Some(request) => write!(f, " while handling \"{}\"", request),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ErrorLog {
+    records: RingBuffer<ErrorRecord>,
+}
+
+impl ErrorLog {
+    pub fn new(capacity: usize) -> Self {
+        Self { records: RingBuffer::new(capacity) }
+    }
+
+    pub fn record(&self, record: ErrorRecord) {
+        self.records.push(record);
+    }
+
+    pub fn records(&self) -> Vec<ErrorRecord> {
+        self.records.snapshot()
+    }
+
+    pub fn clear(&self) {
+        self.records.clear();
+    }
+}
+
+impl Default for ErrorLog {
+    fn default() -> Self {
+        Self::new(64)
+    }
+}
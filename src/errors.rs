@@ -0,0 +1,101 @@
+use std::io::ErrorKind;
+
+use err_derive::Error;
+
+use crate::models::ParseRequestErr;
+#[cfg(feature = "sni")]
+use crate::sni::SniError;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(display = "Invalid request: {}", _0)]
+    Parse(#[source] ParseRequestErr),
+    #[error(display = "Connection error: {}", _0)]
+    Connection(#[source] ConnectionError),
+    #[error(display = "Failed to bind a TCP listener to '{}': {}", _0, _1)]
+    Bind(String, #[error(source, no_from)] std::io::Error),
+    #[error(display = "Failed to accept a connection: {}", _0)]
+    Accept(#[error(source, no_from)] std::io::Error),
+    #[error(display = "The {} task stopped unexpectedly: {}", _0, _1)]
+    Task(&'static str, #[error(source, no_from)] tokio::task::JoinError),
+    #[error(display = "IO error: {}", _0)]
+    Io(#[source] std::io::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConnectionErrorKind {
+    Parse,
+    Timeout,
+    ResetByPeer,
+    LimitExceeded,
+    Io,
+}
+
+impl ConnectionErrorKind {
+    pub const ALL: [Self; 5] = [Self::Parse, Self::Timeout, Self::ResetByPeer, Self::LimitExceeded, Self::Io];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Parse => "parse",
+            Self::Timeout => "timeout",
+            Self::ResetByPeer => "reset",
+            Self::LimitExceeded => "limit",
+            Self::Io => "io",
+        }
+    }
+}
+
+impl std::fmt::Display for ConnectionErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ConnectionError {
+    #[error(display = "Failed to parse request: {}", _0)]
+    Parse(#[source] ParseRequestErr),
+    #[cfg(feature = "sni")]
+    #[error(display = "Failed to route TLS connection: {}", _0)]
+    Sni(#[source] SniError),
+    #[error(display = "Connection timed out")]
+    Timeout,
+    #[error(display = "Timed out waiting for the request body")]
+    BodyTimeout,
+    #[error(display = "Connection reset by peer: {}", _0)]
+    ResetByPeer(#[error(source, no_from)] std::io::Error),
+    #[error(display = "Limit exceeded: {}", _0)]
+    LimitExceeded(String),
+    #[error(display = "Request head is larger than {} bytes", _0)]
+    HeadTooLarge(usize),
+    #[error(display = "Request body is larger than {} bytes", _0)]
+    BodyTooLarge(u64),
+    #[error(display = "IO error: {}", _0)]
+    Io(#[error(source, no_from)] std::io::Error),
+}
+
+impl ConnectionError {
+    pub fn kind(&self) -> ConnectionErrorKind {
+        match self {
+            Self::Parse(_) => ConnectionErrorKind::Parse,
+            #[cfg(feature = "sni")]
+            Self::Sni(_) => ConnectionErrorKind::Parse,
+            Self::Timeout | Self::BodyTimeout => ConnectionErrorKind::Timeout,
+            Self::ResetByPeer(_) => ConnectionErrorKind::ResetByPeer,
+            Self::LimitExceeded(_) | Self::HeadTooLarge(_) | Self::BodyTooLarge(_) => ConnectionErrorKind::LimitExceeded,
+            Self::Io(_) => ConnectionErrorKind::Io,
+        }
+    }
+}
+
+impl From<std::io::Error> for ConnectionError {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            ErrorKind::TimedOut => Self::Timeout,
+            ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted | ErrorKind::BrokenPipe | ErrorKind::UnexpectedEof => Self::ResetByPeer(e),
+            _ => Self::Io(e),
+        }
+    }
+}
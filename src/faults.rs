@@ -0,0 +1,104 @@
+use std::{sync::{atomic::{AtomicBool, Ordering}, RwLock}, time::Duration};
+
+use rand::Rng;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Delay {
+    #[default]
+    None,
+    Fixed(Duration),
+    Uniform { min: Duration, max: Duration },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Fault {
+    Error,
+    Reset,
+    Truncate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FaultConfig {
+    pub delay: Delay,
+    pub error_rate: f64,
+    pub reset_rate: f64,
+    pub truncate_rate: f64,
+}
+
+impl std::fmt::Display for FaultConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.delay {
+            Delay::None => write!(f, "delay: none")?,
+            Delay::Fixed(delay) => write!(f, "delay: {}ms", delay.as_millis())?,
+            Delay::Uniform { min, max } => write!(f, "delay: {}-{}ms", min.as_millis(), max.as_millis())?,
+        }
+
+        write!(f, ", error rate: {}, reset rate: {}, truncate rate: {}", self.error_rate, self.reset_rate, self.truncate_rate)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct FaultInjector {
+    enabled: AtomicBool,
+    config: RwLock<FaultConfig>,
+}
+
+impl FaultInjector {
+    pub fn new(config: FaultConfig) -> Self {
+        Self { enabled: AtomicBool::new(false), config: RwLock::new(config) }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn config(&self) -> FaultConfig {
+        *self.config.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn update(&self, f: impl FnOnce(&mut FaultConfig)) {
+        let mut config = self.config.write().unwrap_or_else(|e| e.into_inner());
+        f(&mut config);
+    }
+
+    pub fn sample_delay(&self) -> Option<Duration> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        match self.config().delay {
+            Delay::None => None,
+            Delay::Fixed(delay) => Some(delay),
+            Delay::Uniform { min, max } if min >= max => Some(min),
+            Delay::Uniform { min, max } => Some(rand::rng().random_range(min..max)),
+        }
+    }
+
+    pub fn sample_fault(&self) -> Option<Fault> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        let config = self.config();
+        let roll: f64 = rand::random();
+        let faults = [
+            (Fault::Reset, config.reset_rate),
+            (Fault::Truncate, config.truncate_rate),
+            (Fault::Error, config.error_rate),
+        ];
+
+        let mut threshold = 0.0;
+        for (fault, rate) in faults {
+            threshold += rate.clamp(0.0, 1.0);
+            if roll < threshold {
+                return Some(fault);
+            }
+        }
+
+        None
+    }
+}
@@ -0,0 +1,754 @@
+use std::{collections::{BTreeMap, BTreeSet}, fmt::Display, net::IpAddr, path::Path, sync::Mutex};
+
+use err_derive::Error;
+
+use crate::{middleware::{Middleware, Next}, models::{HttpRequest, HttpResponse, HttpStatusCode}};
+
+const METADATA_MARKER: &[u8] = b"\xAB\xCD\xEFMaxMind.com";
+const DATA_SECTION_SEPARATOR: usize = 16;
+const MAX_DECODE_DEPTH: usize = 32;
+
+#[derive(Debug, Error)]
+pub enum GeoIpError {
+    #[error(display = "Failed to read the database: {}", _0)]
+    Io(#[source] std::io::Error),
+    #[error(display = "Malformed MaxMind database: {}", _0)]
+    Malformed(&'static str),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    String(String),
+    Double(f64),
+    Bytes(Vec<u8>),
+    Uint(u128),
+    Int(i32),
+    Map(Vec<(String, Value)>),
+    Array(Vec<Value>),
+    Bool(bool),
+}
+
+impl Value {
+    fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Self::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn path(&self, path: &[&str]) -> Option<&Value> {
+        path.iter().try_fold(self, |value, key| value.get(key))
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn as_uint(&self) -> Option<u128> {
+        match self {
+            Self::Uint(n) => Some(*n),
+            Self::Int(n) => u128::try_from(*n).ok(),
+            _ => None,
+        }
+    }
+}
+
+struct Decoder<'a> {
+    data: &'a [u8],
+}
+
+impl Decoder<'_> {
+    fn decode(&self, offset: usize, depth: usize) -> Result<(Value, usize), GeoIpError> {
+        if depth > MAX_DECODE_DEPTH {
+            return Err(GeoIpError::Malformed("data is nested too deeply"));
+        }
+
+        let control = self.byte(offset)?;
+        let mut offset = offset + 1;
+        let mut kind = control >> 5;
+        if kind == 0 {
+            kind = 7 + self.byte(offset)?;
+            offset += 1;
+        }
+
+        if kind == 1 {
+            let size = ((control >> 3) & 0x3) as usize;
+            let bytes = self.slice(offset, size + 1)?;
+            let pointer = match size {
+                0 => (control as usize & 0x7) << 8 | bytes[0] as usize,
+                1 => ((control as usize & 0x7) << 16 | be_uint(bytes) as usize) + 2048,
+                2 => ((control as usize & 0x7) << 24 | be_uint(bytes) as usize) + 526336,
+                _ => be_uint(bytes) as usize,
+            };
+
+            let (value, _) = self.decode(pointer, depth + 1)?;
+            return Ok((value, offset + size + 1));
+        }
+
+        let (size, offset) = match control & 0x1f {
+            29 => (29 + self.byte(offset)? as usize, offset + 1),
+            30 => (285 + be_uint(self.slice(offset, 2)?) as usize, offset + 2),
+            31 => (65821 + be_uint(self.slice(offset, 3)?) as usize, offset + 3),
+            size => (size as usize, offset),
+        };
+
+        match kind {
+            2 => {
+                let text = std::str::from_utf8(self.slice(offset, size)?).map_err(|_| GeoIpError::Malformed("string is not valid UTF-8"))?;
+                Ok((Value::String(text.to_string()), offset + size))
+            },
+            3 => {
+                let bytes = self.slice(offset, 8)?.try_into().map_err(|_| GeoIpError::Malformed("double is not 8 bytes"))?;
+                Ok((Value::Double(f64::from_be_bytes(bytes)), offset + 8))
+            },
+            4 => Ok((Value::Bytes(self.slice(offset, size)?.to_vec()), offset + size)),
+            5 | 6 | 9 | 10 => Ok((Value::Uint(be_uint(self.slice(offset, size)?)), offset + size)),
+            7 => {
+                let mut entries = Vec::with_capacity(size.min(64));
+                let mut offset = offset;
+                for _ in 0..size {
+                    let (key, next) = self.decode(offset, depth + 1)?;
+                    let Value::String(key) = key else {
+                        return Err(GeoIpError::Malformed("map key is not a string"));
+                    };
+
+                    let (value, next) = self.decode(next, depth + 1)?;
+                    entries.push((key, value));
+                    offset = next;
+                }
+
+                Ok((Value::Map(entries), offset))
+            },
+            8 => Ok((Value::Int(be_uint(self.slice(offset, size)?) as u32 as i32), offset + size)),
+            11 => {
+                let mut items = Vec::with_capacity(size.min(64));
+                let mut offset = offset;
+                for _ in 0..size {
+                    let (item, next) = self.decode(offset, depth + 1)?;
+                    items.push(item);
+                    offset = next;
+                }
+
+                Ok((Value::Array(items), offset))
+            },
+            14 => Ok((Value::Bool(size != 0), offset)),
+            15 => {
+                let bytes = self.slice(offset, 4)?.try_into().map_err(|_| GeoIpError::Malformed("float is not 4 bytes"))?;
+                Ok((Value::Double(f32::from_be_bytes(bytes) as f64), offset + 4))
+            },
+            _ => Err(GeoIpError::Malformed("unsupported data type")),
+        }
+    }
+
+    fn byte(&self, offset: usize) -> Result<u8, GeoIpError> {
+        self.data.get(offset).copied().ok_or(GeoIpError::Malformed("data extends past the end of the file"))
+    }
+
+    fn slice(&self, offset: usize, len: usize) -> Result<&[u8], GeoIpError> {
+        offset.checked_add(len)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or(GeoIpError::Malformed("data extends past the end of the file"))
+    }
+}
+
+fn be_uint(bytes: &[u8]) -> u128 {
+    bytes.iter().fold(0, |n, &b| n << 8 | b as u128)
+}
+
+#[derive(Debug)]
+pub struct MmdbReader {
+    buffer: Vec<u8>,
+    node_count: usize,
+    record_size: usize,
+    ip_version: u16,
+    data_start: usize,
+    ipv4_start: usize,
+}
+
+impl MmdbReader {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, GeoIpError> {
+        Self::from_bytes(std::fs::read(path).map_err(GeoIpError::Io)?)
+    }
+
+    pub fn from_bytes(buffer: Vec<u8>) -> Result<Self, GeoIpError> {
+        let metadata_start = buffer.windows(METADATA_MARKER.len())
+            .rposition(|window| window == METADATA_MARKER)
+            .map(|index| index + METADATA_MARKER.len())
+            .ok_or(GeoIpError::Malformed("metadata marker not found"))?;
+
+        let (metadata, _) = Decoder { data: &buffer[metadata_start..] }.decode(0, 0)?;
+        let field = |name: &'static str| metadata.get(name).and_then(Value::as_uint).ok_or(GeoIpError::Malformed("metadata is missing a required field"));
+        let node_count = field("node_count")? as usize;
+        let record_size = field("record_size")? as usize;
+        let ip_version = field("ip_version")? as u16;
+
+        if !matches!(record_size, 24 | 28 | 32) {
+            return Err(GeoIpError::Malformed("unsupported record size"));
+        }
+
+        let tree_size = node_count.checked_mul(record_size / 4)
+            .filter(|size| size + DATA_SECTION_SEPARATOR <= metadata_start)
+            .ok_or(GeoIpError::Malformed("search tree extends into the metadata"))?;
+
+        let mut reader = Self { buffer, node_count, record_size, ip_version, data_start: tree_size + DATA_SECTION_SEPARATOR, ipv4_start: 0 };
+        if ip_version == 6 {
+            let mut node = 0;
+            for _ in 0..96 {
+                if node >= node_count {
+                    break;
+                }
+
+                node = reader.record(node, 0)?;
+            }
+
+            reader.ipv4_start = node;
+        }
+
+        Ok(reader)
+    }
+
+    fn lookup(&self, ip: IpAddr) -> Result<Option<Value>, GeoIpError> {
+        let (bits, start) = match (ip, self.ip_version) {
+            (IpAddr::V4(ip), 4) => (u32::from(ip) as u128, (32, 0)),
+            (IpAddr::V4(ip), _) => (u32::from(ip) as u128, (32, self.ipv4_start)),
+            (IpAddr::V6(ip), 6) => (u128::from(ip), (128, 0)),
+            (IpAddr::V6(ip), _) => match ip.to_ipv4_mapped() {
+                Some(ip) => (u32::from(ip) as u128, (32, 0)),
+                None => return Ok(None),
+            },
+        };
+
+        let (len, mut node) = start;
+        for index in (0..len).rev() {
+            if node >= self.node_count {
+                break;
+            }
+
+            node = self.record(node, (bits >> index) as usize & 1)?;
+        }
+
+        match node.cmp(&self.node_count) {
+            std::cmp::Ordering::Equal => Ok(None),
+            std::cmp::Ordering::Less => Err(GeoIpError::Malformed("search tree is deeper than the address")),
+            std::cmp::Ordering::Greater => {
+                let offset = node.checked_sub(self.node_count + DATA_SECTION_SEPARATOR)
+                    .ok_or(GeoIpError::Malformed("record points into the data section separator"))?;
+                let (value, _) = Decoder { data: &self.buffer[self.data_start..] }.decode(offset, 0)?;
+                Ok(Some(value))
+            },
+        }
+    }
+
+    fn record(&self, node: usize, bit: usize) -> Result<usize, GeoIpError> {
+        let node_size = self.record_size / 4;
+        let base = node * node_size;
+        let bytes = self.buffer.get(base..base + node_size).ok_or(GeoIpError::Malformed("search tree is truncated"))?;
+        let record = match (self.record_size, bit) {
+            (24, _) => be_uint(&bytes[bit * 3..bit * 3 + 3]),
+            (28, 0) => (bytes[3] as u128 & 0xF0) << 20 | be_uint(&bytes[0..3]),
+            (28, _) => (bytes[3] as u128 & 0x0F) << 24 | be_uint(&bytes[4..7]),
+            (_, _) => be_uint(&bytes[bit * 4..bit * 4 + 4]),
+        };
+
+        Ok(record as usize)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GeoInfo {
+    pub country: Option<String>,
+    pub asn: Option<u32>,
+    pub organization: Option<String>,
+}
+
+impl GeoInfo {
+    pub fn from_request(request: &HttpRequest) -> Option<&Self> {
+        request.extensions().get()
+    }
+
+    fn merge(&mut self, value: &Value) {
+        if self.country.is_none() {
+            self.country = value.path(&["country", "iso_code"])
+                .or_else(|| value.path(&["registered_country", "iso_code"]))
+                .and_then(Value::as_str)
+                .map(str::to_ascii_uppercase);
+        }
+
+        if self.asn.is_none() {
+            self.asn = value.get("autonomous_system_number").and_then(Value::as_uint).and_then(|asn| asn.try_into().ok());
+        }
+
+        if self.organization.is_none() {
+            self.organization = value.get("autonomous_system_organization").and_then(Value::as_str).map(str::to_string);
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.country.is_none() && self.asn.is_none()
+    }
+}
+
+impl Display for GeoInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.country, self.asn) {
+            (Some(country), Some(asn)) => write!(f, "{}/AS{}", country, asn),
+            (Some(country), None) => write!(f, "{}", country),
+            (None, Some(asn)) => write!(f, "AS{}", asn),
+            (None, None) => write!(f, "-"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GeoIp {
+    databases: Vec<MmdbReader>,
+    countries: Mutex<BTreeMap<String, u64>>,
+}
+
+impl GeoIp {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_database(mut self, database: MmdbReader) -> Self {
+        self.databases.push(database);
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.databases.is_empty()
+    }
+
+    pub fn lookup(&self, ip: IpAddr) -> Option<GeoInfo> {
+        let mut info = GeoInfo::default();
+        for database in &self.databases {
+            match database.lookup(ip) {
+                Ok(Some(value)) => info.merge(&value),
+                Ok(None) => {},
+                Err(e) => log::warn!("GeoIP lookup for {} failed: {}", ip, e),
+            }
+        }
+
+        (!info.is_empty()).then_some(info)
+    }
+
+    pub fn tag(&self, request: &mut HttpRequest, ip: IpAddr) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let info = self.lookup(ip);
+        let country = info.as_ref().and_then(|info| info.country.clone()).unwrap_or_else(|| String::from("unknown"));
+        *self.lock().entry(country).or_default() += 1;
+
+        if let Some(info) = info {
+            request.extensions_mut().insert(info);
+        }
+    }
+
+    pub fn countries(&self) -> BTreeMap<String, u64> {
+        self.lock().clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, u64>> {
+        self.countries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Display for GeoIp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} databases", self.databases.len())?;
+        for (country, count) in self.lock().iter() {
+            write!(f, ", {}: {}", country, count)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegionPolicy {
+    allow: BTreeSet<String>,
+    deny: BTreeSet<String>,
+}
+
+impl RegionPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn allow(mut self, region: impl Display) -> Self {
+        self.allow.insert(region.to_string().to_ascii_uppercase());
+        self
+    }
+
+    pub fn deny(mut self, region: impl Display) -> Self {
+        self.deny.insert(region.to_string().to_ascii_uppercase());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    // Regions are ISO country codes or AS numbers written as "AS15169".
+    pub fn permits(&self, info: Option<&GeoInfo>) -> bool {
+        let regions = info.map(|info| {
+            let country = info.country.clone();
+            let asn = info.asn.map(|asn| format!("AS{}", asn));
+            country.into_iter().chain(asn).collect::<Vec<_>>()
+        }).unwrap_or_default();
+
+        if regions.iter().any(|region| self.deny.contains(region)) {
+            return false;
+        }
+
+        self.allow.is_empty() || regions.iter().any(|region| self.allow.contains(region))
+    }
+}
+
+impl Middleware for RegionPolicy {
+    async fn handle(&self, request: &mut HttpRequest, next: Next<'_>) -> HttpResponse {
+        match self.check_head(request) {
+            Some(response) => response,
+            None => next.run(request).await,
+        }
+    }
+
+    fn check_head(&self, request: &mut HttpRequest) -> Option<HttpResponse> {
+        if self.permits(GeoInfo::from_request(request)) {
+            return None;
+        }
+
+        Some(HttpResponse::new(HttpStatusCode::Forbidden, HttpStatusCode::Forbidden.get_readable_name()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    use super::*;
+
+    #[derive(Clone, Copy)]
+    enum Record {
+        Empty,
+        Node(usize),
+        Data(usize),
+        Raw(usize),
+    }
+
+    struct Tree {
+        nodes: Vec<[Record; 2]>,
+    }
+
+    impl Tree {
+        fn new() -> Self {
+            Self { nodes: vec![[Record::Empty; 2]] }
+        }
+
+        fn insert(&mut self, bits: &[usize], record: Record) {
+            let mut node = 0;
+            for (index, &bit) in bits.iter().enumerate() {
+                if index + 1 == bits.len() {
+                    self.nodes[node][bit] = record;
+                    return;
+                }
+
+                node = match self.nodes[node][bit] {
+                    Record::Node(next) => next,
+                    _ => {
+                        self.nodes.push([Record::Empty; 2]);
+                        let next = self.nodes.len() - 1;
+                        self.nodes[node][bit] = Record::Node(next);
+                        next
+                    },
+                };
+            }
+        }
+
+        fn encode(&self, record_size: usize) -> Vec<u8> {
+            let node_count = self.nodes.len();
+            let value = |record: Record| match record {
+                Record::Empty => node_count,
+                Record::Node(node) => node,
+                Record::Data(offset) => node_count + DATA_SECTION_SEPARATOR + offset,
+                Record::Raw(value) => value,
+            };
+
+            let mut output = Vec::new();
+            for [left, right] in &self.nodes {
+                let (left, right) = (value(*left) as u32, value(*right) as u32);
+                match record_size {
+                    24 => {
+                        output.extend_from_slice(&left.to_be_bytes()[1..]);
+                        output.extend_from_slice(&right.to_be_bytes()[1..]);
+                    },
+                    28 => {
+                        output.extend_from_slice(&left.to_be_bytes()[1..]);
+                        output.push(((left >> 20) & 0xF0) as u8 | ((right >> 24) & 0x0F) as u8);
+                        output.extend_from_slice(&right.to_be_bytes()[1..]);
+                    },
+                    _ => {
+                        output.extend_from_slice(&left.to_be_bytes());
+                        output.extend_from_slice(&right.to_be_bytes());
+                    },
+                }
+            }
+
+            output
+        }
+    }
+
+    fn string(text: &str) -> Vec<u8> {
+        let mut output = match text.len() {
+            0..29 => vec![(2 << 5) | text.len() as u8],
+            len => vec![(2 << 5) | 29, (len - 29) as u8],
+        };
+
+        output.extend_from_slice(text.as_bytes());
+        output
+    }
+
+    fn uint(kind: u8, value: u32) -> Vec<u8> {
+        let bytes = value.to_be_bytes();
+        let bytes = &bytes[bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len())..];
+        let mut output = vec![(kind << 5) | bytes.len() as u8];
+        output.extend_from_slice(bytes);
+        output
+    }
+
+    fn map(entries: &[(Vec<u8>, Vec<u8>)]) -> Vec<u8> {
+        // Maps are an extended type: 7 is stored as 7 - 7 in the byte after the control byte.
+        let mut output = vec![entries.len() as u8, 0];
+        for (key, value) in entries {
+            output.extend_from_slice(key);
+            output.extend_from_slice(value);
+        }
+
+        output
+    }
+
+    fn padding(len: usize) -> Vec<u8> {
+        let mut output = vec![(4 << 5) | 30];
+        output.extend_from_slice(&((len - 285) as u16).to_be_bytes());
+        output.resize(output.len() + len, 0);
+        output
+    }
+
+    fn pointer(offset: usize) -> Vec<u8> {
+        match offset {
+            0..2048 => vec![(1 << 5) | (offset >> 8) as u8, offset as u8],
+            _ => {
+                let offset = offset - 2048;
+                vec![(1 << 5) | (1 << 3) | (offset >> 16) as u8, (offset >> 8) as u8, offset as u8]
+            },
+        }
+    }
+
+    fn database(tree: &Tree, data: &[u8], record_size: usize, ip_version: u32) -> Vec<u8> {
+        let metadata = map(&[
+            (string("node_count"), uint(6, tree.nodes.len() as u32)),
+            (string("record_size"), uint(5, record_size as u32)),
+            (string("ip_version"), uint(5, ip_version)),
+        ]);
+
+        let mut output = tree.encode(record_size);
+        output.extend_from_slice(&[0; DATA_SECTION_SEPARATOR]);
+        output.extend_from_slice(data);
+        output.extend_from_slice(METADATA_MARKER);
+        output.extend_from_slice(&metadata);
+        output
+    }
+
+    fn bits(value: u128, len: usize, prefix: usize) -> Vec<usize> {
+        (0..prefix).map(|index| (value >> (len - 1 - index)) as usize & 1).collect()
+    }
+
+    fn v4_bits(ip: Ipv4Addr, prefix: usize) -> Vec<usize> {
+        bits(u32::from(ip) as u128, 32, prefix)
+    }
+
+    fn v6_bits(ip: Ipv6Addr, prefix: usize) -> Vec<usize> {
+        bits(u128::from(ip), 128, prefix)
+    }
+
+    // Two records: the first names its country through a short pointer, the second
+    // through a long pointer past a block of padding, with the key itself a pointer.
+    struct Fixture {
+        data: Vec<u8>,
+        first: usize,
+        second: usize,
+    }
+
+    fn fixture() -> Fixture {
+        let mut data = map(&[(string("iso_code"), string("nz"))]);
+        let key = data.len();
+        data.extend(string("country"));
+        data.extend(padding(2100));
+
+        let far = data.len();
+        data.extend(map(&[(string("iso_code"), string("au"))]));
+
+        let first = data.len();
+        data.extend(map(&[
+            (string("country"), pointer(0)),
+            (string("autonomous_system_number"), uint(6, 64512)),
+            (string("autonomous_system_organization"), string("Example Net")),
+        ]));
+
+        let second = data.len();
+        data.extend(map(&[
+            (pointer(key), pointer(far)),
+            (string("autonomous_system_number"), uint(6, 70000)),
+        ]));
+
+        Fixture { data, first, second }
+    }
+
+    fn v4_database(record_size: usize) -> MmdbReader {
+        let fixture = fixture();
+        let mut tree = Tree::new();
+        tree.insert(&v4_bits(Ipv4Addr::new(192, 0, 2, 0), 24), Record::Data(fixture.first));
+        tree.insert(&v4_bits(Ipv4Addr::new(198, 51, 100, 0), 24), Record::Data(fixture.second));
+        MmdbReader::from_bytes(database(&tree, &fixture.data, record_size, 4)).expect("Fixture database should open")
+    }
+
+    fn info(country: &str, asn: u32, organization: Option<&str>) -> GeoInfo {
+        GeoInfo { country: Some(country.to_string()), asn: Some(asn), organization: organization.map(str::to_string) }
+    }
+
+    fn lookup(reader: MmdbReader, ip: impl Into<IpAddr>) -> Option<GeoInfo> {
+        GeoIp::new().with_database(reader).lookup(ip.into())
+    }
+
+    #[test]
+    fn lookups_follow_short_and_long_pointers() {
+        let geoip = GeoIp::new().with_database(v4_database(24));
+        assert_eq!(geoip.lookup(Ipv4Addr::new(192, 0, 2, 77).into()), Some(info("NZ", 64512, Some("Example Net"))));
+        assert_eq!(geoip.lookup(Ipv4Addr::new(198, 51, 100, 1).into()), Some(info("AU", 70000, None)));
+        assert_eq!(geoip.lookup(Ipv4Addr::new(203, 0, 113, 1).into()), None);
+    }
+
+    #[test]
+    fn all_record_sizes_decode() {
+        for record_size in [24, 28, 32] {
+            let reader = v4_database(record_size);
+            assert_eq!(reader.record_size, record_size);
+            assert_eq!(lookup(reader, Ipv4Addr::new(192, 0, 2, 1)).and_then(|info| info.asn), Some(64512), "{}-bit records", record_size);
+        }
+    }
+
+    #[test]
+    fn large_28_bit_records_keep_their_high_nibble() {
+        let (left, right) = (0x0ABC_DEF1_u32, 0x0123_4567_u32);
+        let mut node = left.to_be_bytes()[1..].to_vec();
+        node.push(((left >> 20) & 0xF0) as u8 | ((right >> 24) & 0x0F) as u8);
+        node.extend_from_slice(&right.to_be_bytes()[1..]);
+
+        let reader = MmdbReader { buffer: node, node_count: 1 << 27, record_size: 28, ip_version: 4, data_start: 0, ipv4_start: 0 };
+        assert_eq!(reader.record(0, 0).unwrap(), left as usize);
+        assert_eq!(reader.record(0, 1).unwrap(), right as usize);
+    }
+
+    #[test]
+    fn ipv4_addresses_use_the_ipv4_subtree_of_ipv6_databases() {
+        let fixture = fixture();
+        let mut tree = Tree::new();
+        let mut prefix = vec![0; 96];
+        prefix.extend(v4_bits(Ipv4Addr::new(192, 0, 2, 0), 24));
+        tree.insert(&prefix, Record::Data(fixture.first));
+        tree.insert(&v6_bits("2001:db8::".parse().unwrap(), 32), Record::Data(fixture.second));
+
+        let geoip = GeoIp::new().with_database(MmdbReader::from_bytes(database(&tree, &fixture.data, 24, 6)).unwrap());
+        assert_eq!(geoip.lookup(Ipv4Addr::new(192, 0, 2, 9).into()).and_then(|info| info.asn), Some(64512));
+        assert_eq!(geoip.lookup("2001:db8::1".parse().unwrap()).and_then(|info| info.asn), Some(70000));
+        assert_eq!(geoip.lookup("2001:db9::1".parse().unwrap()), None);
+    }
+
+    #[test]
+    fn mapped_ipv6_addresses_use_ipv4_databases() {
+        assert_eq!(lookup(v4_database(24), Ipv4Addr::new(192, 0, 2, 1).to_ipv6_mapped()).and_then(|info| info.asn), Some(64512));
+        assert_eq!(lookup(v4_database(24), "2001:db8::1".parse::<Ipv6Addr>().unwrap()), None);
+    }
+
+    #[test]
+    fn databases_are_merged_in_order() {
+        let fixture = fixture();
+        let mut tree = Tree::new();
+        tree.insert(&v4_bits(Ipv4Addr::new(192, 0, 2, 0), 24), Record::Data(fixture.second));
+        let second = MmdbReader::from_bytes(database(&tree, &fixture.data, 24, 4)).unwrap();
+
+        let geoip = GeoIp::new().with_database(v4_database(24)).with_database(second);
+        assert_eq!(geoip.lookup(Ipv4Addr::new(192, 0, 2, 1).into()), Some(info("NZ", 64512, Some("Example Net"))));
+    }
+
+    #[test]
+    fn records_pointing_into_the_separator_are_rejected() {
+        let fixture = fixture();
+        let prefix = v4_bits(Ipv4Addr::new(192, 0, 2, 0), 24);
+        let mut tree = Tree::new();
+        tree.insert(&prefix, Record::Empty);
+        let separator = tree.nodes.len() + 3;
+        tree.insert(&prefix, Record::Raw(separator));
+
+        let reader = MmdbReader::from_bytes(database(&tree, &fixture.data, 24, 4)).unwrap();
+        assert!(matches!(reader.lookup(Ipv4Addr::new(192, 0, 2, 1).into()), Err(GeoIpError::Malformed(_))));
+    }
+
+    #[test]
+    fn records_pointing_past_the_data_are_rejected() {
+        let fixture = fixture();
+        let mut tree = Tree::new();
+        tree.insert(&v4_bits(Ipv4Addr::new(192, 0, 2, 0), 24), Record::Data(1 << 20));
+
+        let reader = MmdbReader::from_bytes(database(&tree, &fixture.data, 24, 4)).unwrap();
+        assert!(matches!(reader.lookup(Ipv4Addr::new(192, 0, 2, 1).into()), Err(GeoIpError::Malformed(_))));
+    }
+
+    #[test]
+    fn pointer_cycles_are_rejected() {
+        let mut data = pointer(2);
+        data.extend(pointer(0));
+        let mut tree = Tree::new();
+        tree.insert(&v4_bits(Ipv4Addr::new(192, 0, 2, 0), 24), Record::Data(0));
+
+        let reader = MmdbReader::from_bytes(database(&tree, &data, 24, 4)).unwrap();
+        assert!(matches!(reader.lookup(Ipv4Addr::new(192, 0, 2, 1).into()), Err(GeoIpError::Malformed(_))));
+    }
+
+    #[test]
+    fn truncated_databases_fail_to_open() {
+        let bytes = database(&Tree::new(), &fixture().data, 24, 4);
+        let marker = bytes.windows(METADATA_MARKER.len()).rposition(|window| window == METADATA_MARKER).unwrap();
+
+        assert!(MmdbReader::from_bytes(bytes[..marker].to_vec()).is_err());
+        assert!(MmdbReader::from_bytes(bytes[..marker + METADATA_MARKER.len() + 4].to_vec()).is_err());
+        assert!(MmdbReader::from_bytes(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn oversized_trees_and_record_sizes_fail_to_open() {
+        let mut tree = Tree::new();
+        tree.nodes.resize(64, [Record::Empty; 2]);
+        let mut bytes = database(&tree, &[], 24, 4);
+        // Keep a single node so the metadata claims more of the tree than the file holds.
+        bytes.drain(..63 * 6);
+        assert!(matches!(MmdbReader::from_bytes(bytes), Err(GeoIpError::Malformed(_))));
+
+        assert!(matches!(MmdbReader::from_bytes(database(&Tree::new(), &[], 16, 4)), Err(GeoIpError::Malformed(_))));
+    }
+
+    #[test]
+    fn region_policies_deny_before_allowing() {
+        let nz = info("NZ", 64512, None);
+        let policy = RegionPolicy::new().allow("nz").deny("AS64512");
+        assert!(!policy.permits(Some(&nz)));
+        assert!(RegionPolicy::new().allow("nz").permits(Some(&nz)));
+        assert!(!RegionPolicy::new().allow("nz").permits(None));
+        assert!(RegionPolicy::new().deny("au").permits(None));
+    }
+}
@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use crate::models::{HttpRequest, HttpResponse};
+
+type HookFn = Arc<dyn Fn(Option<&HttpRequest>, &mut HttpResponse) + Send + Sync>;
+
+#[derive(Clone)]
+struct Hook {
+    name: String,
+    run: HookFn,
+}
+
+#[derive(Clone, Default)]
+pub struct ResponseHooks {
+    hooks: Vec<Hook>,
+}
+
+impl ResponseHooks {
+    pub fn new() -> Self {
+        Self { hooks: Vec::new() }
+    }
+
+    pub fn add<F>(&mut self, name: impl std::fmt::Display, hook: F) -> &mut Self
+    where
+        F: Fn(Option<&HttpRequest>, &mut HttpResponse) + Send + Sync + 'static,
+    {
+        self.hooks.push(Hook { name: name.to_string(), run: Arc::new(hook) });
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.hooks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    pub fn apply(&self, request: Option<&HttpRequest>, response: &mut HttpResponse) {
+        for hook in &self.hooks {
+            (hook.run)(request, response);
+        }
+    }
+}
+
+impl std::fmt::Debug for ResponseHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.hooks.iter().map(|hook| &hook.name))
+            .finish()
+    }
+}
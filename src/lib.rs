@@ -0,0 +1,38 @@
+#![allow(non_local_definitions)]
+
+pub mod access_log;
+pub mod admission;
+pub mod app;
+pub mod auth;
+#[cfg(feature = "body-checksum")]
+pub mod checksum;
+pub mod conditional;
+pub mod config;
+pub mod console;
+pub mod error_log;
+pub mod errors;
+pub mod faults;
+#[cfg(feature = "geoip")]
+pub mod geoip;
+pub mod hooks;
+pub mod logger;
+pub mod penalty;
+pub mod redact;
+pub mod ring;
+pub mod sampling;
+pub mod scheduler;
+pub mod server;
+#[cfg(feature = "sni")]
+pub mod sni;
+pub mod static_files;
+pub mod statsd;
+pub mod stats;
+pub mod streaming;
+pub mod throttle;
+pub mod trace;
+pub mod transport;
+
+pub use http_types as models;
+pub use http_types::{memory, middleware, router, uri};
+pub use http_types::{HttpRequest, HttpResponse, ParserProfile, RequestParser, ResponseParser};
+pub use server::Server;
@@ -1,110 +1,70 @@
-#![allow(non_local_definitions)]
-
-mod models;
-
-use std::net::SocketAddr;
-
-use models::HttpResponse;
-use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
-
-const HOST_ADDR_VARIABLE: &str = "HOST_ADDR";
-
-#[tokio::main]
-async fn main() {
-    let address = get_host_addr();
-    let result = tokio::select! {
-        res = tokio::spawn(run_console()) => res,
-        res = tokio::spawn(run_server(address)) => res,
-    };
-
-    result.expect("An error occurred while running the server");
+use std::{path::PathBuf, process::ExitCode};
+
+use clap::Parser;
+use log::LevelFilter;
+
+use rust_http_server::{app, config::{self, Env, Options}, logger};
+
+/// A small HTTP/1.1 server. Options not given here are read from environment variables,
+/// which can also be loaded from a --config file.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Address to listen on, such as 127.0.0.1:8080 (defaults to $HOST_ADDR)
+    #[arg(value_name = "ADDR")]
+    addr: Option<String>,
+    /// Host or IP address to bind to, replacing the host part of the address
+    #[arg(long, value_name = "HOST")]
+    bind: Option<String>,
+    /// Port to listen on, replacing the port part of the address
+    #[arg(long, short, value_name = "PORT")]
+    port: Option<u16>,
+    /// Directory served under /static (defaults to $STATIC_ROOT)
+    #[arg(long, value_name = "DIR")]
+    static_dir: Option<PathBuf>,
+    /// One of off, error, warn, info, debug or trace (defaults to $LOG_LEVEL, then warn)
+    #[arg(long, value_name = "LEVEL", value_parser = config::parse_log_level)]
+    log_level: Option<LevelFilter>,
+    /// File of KEY=VALUE lines used for settings not already set in the environment
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
 }
 
-fn get_host_addr() -> String {
-    if let Some(addr) = std::env::args().nth(1) {
-        addr
-    } else if let Ok(addr) = std::env::var(HOST_ADDR_VARIABLE) {
-        addr
-    } else {
-        String::from("127.0.0.1:8080")
-    }
-}
-
-async fn run_console() {
-    let stdin = std::io::stdin();
-
-    loop {
-        let mut command = String::new();
-        if let Ok(_) = stdin.read_line(&mut command) {
-            let parts = command
-                .split_whitespace()
-                .filter(|s| !s.trim().is_empty())
-                .collect::<Vec<_>>();
-
-            match parts.get(0).map(|s| *s) {
-                Some("quit" | "q" | "stop") => break,
-                _ => ()
-            }
-        }
-    }
-}
-
-async fn run_server(addr: impl ToSocketAddrs) {
-    let listener = match TcpListener::bind(addr).await {
-        Ok(listener) => listener,
-        Err(e) => {
-            log::error!("Failed to bind TCP listener: {}", e);
-            return;
-        }
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let options = Options {
+        addr: cli.addr,
+        bind: cli.bind,
+        port: cli.port,
+        static_dir: cli.static_dir,
+        log_level: cli.log_level,
     };
 
-    while let Ok((stream, addr)) = listener.accept().await {
-        tokio::spawn(handle_connection_wrapper(stream, addr));
+    let mut env = Env::from_process();
+    if let Some(path) = &cli.config {
+        if let Err(e) = env.load_file(path) {
+            eprintln!("Error: Failed to load the config file '{}': {}", path.display(), e);
+            return ExitCode::FAILURE;
+        }
     }
-}
 
-async fn handle_connection_wrapper(stream: TcpStream, addr: SocketAddr) {
-    if let Err(e) = handle_connection(stream, addr).await {
-        log::error!("An error occurred while handling the connection for {}: {}", addr, e);
+    if let Err(e) = logger::init(config::log_level(&env, &options)) {
+        eprintln!("Failed to install the logger: {}", e);
     }
-}
 
-async fn handle_connection(stream: TcpStream, addr: SocketAddr) -> anyhow::Result<()> {
-    println!("Connection established with {}", addr);
-
-    stream.readable().await?;
-    let message = read_all(&stream)?;
-    
-    match String::from_utf8(message) {
-        Err(_) => println!("Received byte message."),
-        Ok(msg) => {
-            let model = models::HttpRequest::new(&msg);
-            println!("{:#?}", model);
-        },
-    }
-
-    let response = HttpResponse::im_a_teapot("Hello!").to_string();
-
-    stream.writable().await?;
-    stream.try_write(response.as_bytes())?;
-
-    println!("Connection with {} closed", addr);
-
-    Ok(())
+    start(env, options)
 }
 
-fn read_all(stream: &TcpStream) -> anyhow::Result<Vec<u8>> {
-    let mut output_buffer = Vec::new();
+#[tokio::main]
+async fn start(env: Env, options: Options) -> ExitCode {
+    #[cfg(feature = "tokio-console")]
+    console_subscriber::init();
 
-    loop {
-        let mut temp_buffer = [0_u8; 4096];
-        match stream.try_read(&mut temp_buffer) {
-            Ok(0) => break,
-            Ok(count) => output_buffer.extend_from_slice(&temp_buffer[0..count]),
-            Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
-            Err(e) => return Err(e.into())
+    match app::run(&env, &options).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
         }
     }
-
-    Ok(output_buffer)
 }
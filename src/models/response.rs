@@ -1,177 +0,0 @@
-use std::collections::HashMap;
-
-use super::HttpVersion;
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-#[allow(dead_code)]
-pub enum HttpStatusCode {
-    Continue = 100,
-    SwitchingProtocols = 101,
-    Processing = 102,
-    EarlyHints = 103,
-    OK = 200,
-    Created = 201,
-    Accepted = 202,
-    NonAuthoritativeInformation = 203,
-    NoContent = 204,
-    ResetContent = 205,
-    PartialContent = 206,
-    MultiStatus = 207,
-    AlreadyReported = 208,
-    ImUsed = 226,
-    MultipleChoices = 300,
-    MovedPermanently = 301,
-    Found = 302,
-    SeeOther = 303,
-    NotModified = 304,
-    UseProxy = 305,
-    Unused = 306,
-    TemporaryRedirect = 307,
-    PermanentRedirect = 308,
-    BadRequest = 400,
-    Unauthorized = 401,
-    PaymentRequired = 402,
-    Forbidden = 403,
-    NotFound = 404,
-    MethodNotAllowed = 405,
-    NotAcceptable = 406,
-    ProxyAuthenticationRequired = 407,
-    RequestTimeout = 408,
-    Conflict = 409,
-    Gone = 410,
-    LengthRequired = 411,
-    PreconditionFailed = 412,
-    ContentTooLarge = 413,
-    UriTooLong = 414,
-    UnsupportedMediaType = 415,
-    RangeNotSatisfiable = 416,
-    ExpectationFailed = 417,
-    ImATeapot = 418,
-    MisdirectedRequest = 421,
-    UnprocessableContent = 422,
-    Locked = 423,
-    FailedDependency = 424,
-    TooEarly = 425,
-    UpgradeRequired = 426,
-    PreconditionRequired = 428,
-    TooManyRequests = 429,
-    RequestHeaderFieldsTooLarge = 431,
-    UnavailableForLegalReasons = 451,
-    InternalServerError = 500,
-    NotImplemented = 501,
-    BadGateway = 502,
-    ServiceUnavailable = 503,
-    GatewayTimeout = 504,
-    HTTPVersionNotSupported = 505,
-    VariantAlsoNegotiates = 506,
-    InsufficientStorage = 507,
-    LoopDetected = 508,
-    NotExtended = 510,
-    NetworkAuthenticationRequired = 511,
-}
-
-impl HttpStatusCode {
-    pub fn get_readable_name(self) -> &'static str {
-        match self {
-            Self::Continue => "Continue",
-            Self::SwitchingProtocols => "Switching Protocols",
-            Self::Processing => "Processing",
-            Self::EarlyHints => "Early Hints",
-            Self::OK => "OK",
-            Self::Created => "Created",
-            Self::Accepted => "Accepted",
-            Self::NonAuthoritativeInformation => "Non-Authoritative Information",
-            Self::NoContent => "No Content",
-            Self::ResetContent => "Reset Content",
-            Self::PartialContent => "Partial Content",
-            Self::MultiStatus => "Multi-Status",
-            Self::AlreadyReported => "Already Reported",
-            Self::ImUsed => "IM Used",
-            Self::MultipleChoices => "Multiple Choices",
-            Self::MovedPermanently => "Moved Permanently",
-            Self::Found => "Found",
-            Self::SeeOther => "See Other",
-            Self::NotModified => "Not Modified",
-            Self::UseProxy => "Use Proxy",
-            Self::Unused => "unused",
-            Self::TemporaryRedirect => "Temporary Redirect",
-            Self::PermanentRedirect => "Permanent Redirect",
-            Self::BadRequest => "Bad Request",
-            Self::Unauthorized => "Unauthorized",
-            Self::PaymentRequired => "Payment Required",
-            Self::Forbidden => "Forbidden",
-            Self::NotFound => "Not Found",
-            Self::MethodNotAllowed => "Method Not Allowed",
-            Self::NotAcceptable => "Not Acceptable",
-            Self::ProxyAuthenticationRequired => "Proxy Authentication Required",
-            Self::RequestTimeout => "Request Timeout",
-            Self::Conflict => "Conflict",
-            Self::Gone => "Gone",
-            Self::LengthRequired => "Length Required",
-            Self::PreconditionFailed => "Precondition Failed",
-            Self::ContentTooLarge => "Content Too Large",
-            Self::UriTooLong => "URI Too Long",
-            Self::UnsupportedMediaType => "Unsupported Media Type",
-            Self::RangeNotSatisfiable => "Range Not Satisfiable",
-            Self::ExpectationFailed => "Expectation Failed",
-            Self::ImATeapot => "I'm a teapot",
-            Self::MisdirectedRequest => "Misdirected Request",
-            Self::UnprocessableContent => "Unprocessable Content",
-            Self::Locked => "Locked",
-            Self::FailedDependency => "Failed Dependency",
-            Self::TooEarly => "Too Early",
-            Self::UpgradeRequired => "Upgrade Required",
-            Self::PreconditionRequired => "Precondition Required",
-            Self::TooManyRequests => "Too Many Requests",
-            Self::RequestHeaderFieldsTooLarge => "Request Header Fields Too Large",
-            Self::UnavailableForLegalReasons => "Unavailable For Legal Reasons",
-            Self::InternalServerError => "Internal Server Error",
-            Self::NotImplemented => "Not Implemented",
-            Self::BadGateway => "Bad Gateway",
-            Self::ServiceUnavailable => "Service Unavailable",
-            Self::GatewayTimeout => "Gateway Timeout",
-            Self::HTTPVersionNotSupported => "HTTP Version Not Supported",
-            Self::VariantAlsoNegotiates => "Variant Also Negotiates",
-            Self::InsufficientStorage => "Insufficient Storage",
-            Self::LoopDetected => "Loop Detected",
-            Self::NotExtended => "Not Extended",
-            Self::NetworkAuthenticationRequired => "Network Authentication Required",
-        }
-    }
-}
-
-impl std::fmt::Display for HttpStatusCode {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} {}", *self as usize, self.get_readable_name())
-    }
-}
-
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct HttpResponse {
-    status: HttpStatusCode,
-    version: HttpVersion,
-    headers: HashMap<String, String>,
-    body: String
-}
-
-impl HttpResponse {
-    pub fn im_a_teapot(body: impl std::fmt::Display) -> Self {
-        Self {
-            status: HttpStatusCode::ImATeapot,
-            version: HttpVersion::new(1, 1),
-            headers: HashMap::new(),
-            body: body.to_string()
-        }
-    }
-}
-
-impl std::fmt::Display for HttpResponse {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} {}\r\n", self.version, self.status)?;
-        for (key, val) in self.headers.iter() {
-            write!(f, "{}: {}\r\n", key, val)?;
-        }
-
-        write!(f, "\r\n{}", self.body)
-    }
-}
\ No newline at end of file
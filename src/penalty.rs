@@ -0,0 +1,91 @@
+use std::{collections::HashMap, net::IpAddr, sync::Mutex, time::{Duration, Instant}};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PenaltyConfig {
+    pub threshold: u32,
+    pub window: Duration,
+    pub duration: Duration,
+}
+
+impl Default for PenaltyConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 20,
+            window: Duration::from_secs(60),
+            duration: Duration::from_secs(300),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct OffenseRecord {
+    count: u32,
+    window_start: Instant,
+    penalized_until: Option<Instant>,
+}
+
+#[derive(Debug, Default)]
+pub struct PenaltyBox {
+    config: PenaltyConfig,
+    records: Mutex<HashMap<IpAddr, OffenseRecord>>,
+}
+
+impl PenaltyBox {
+    pub fn new(config: PenaltyConfig) -> Self {
+        Self { config, records: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.threshold > 0
+    }
+
+    pub fn record_offense(&self, addr: IpAddr) -> bool {
+        if !self.is_enabled() {
+            return false;
+        }
+
+        let now = Instant::now();
+        let mut records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        let record = records.entry(addr).or_insert(OffenseRecord { count: 0, window_start: now, penalized_until: None });
+
+        if now.duration_since(record.window_start) > self.config.window {
+            record.count = 0;
+            record.window_start = now;
+        }
+
+        record.count += 1;
+        if record.count >= self.config.threshold {
+            record.penalized_until = Some(now + self.config.duration);
+            record.count = 0;
+            record.window_start = now;
+            return true;
+        }
+
+        false
+    }
+
+    pub fn is_penalized(&self, addr: IpAddr) -> bool {
+        let records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        records.get(&addr)
+            .and_then(|record| record.penalized_until)
+            .is_some_and(|until| Instant::now() < until)
+    }
+
+    pub fn penalized(&self) -> usize {
+        let now = Instant::now();
+        let records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        records.values()
+            .filter(|record| record.penalized_until.is_some_and(|until| now < until))
+            .count()
+    }
+
+    pub fn sweep(&self) {
+        let now = Instant::now();
+        let mut records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        records.retain(|_, record| {
+            let penalized = record.penalized_until.is_some_and(|until| now < until);
+            let counting = now.duration_since(record.window_start) <= self.config.window;
+            penalized || counting
+        });
+    }
+}
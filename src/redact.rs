@@ -0,0 +1,219 @@
+use std::{borrow::Cow, collections::BTreeSet, fmt::{Debug, Display}};
+
+#[cfg(feature = "body-redaction")]
+use regex::Regex;
+
+use crate::models::{Body, HeaderMap, HttpRequest, HttpResponse};
+
+const REDACTED: &str = "[REDACTED]";
+const DEFAULT_SENSITIVE_HEADERS: [&str; 4] = ["authorization", "proxy-authorization", "cookie", "set-cookie"];
+
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    headers: BTreeSet<String>,
+    #[cfg(feature = "body-redaction")]
+    body_patterns: Vec<Regex>,
+}
+
+impl Default for Redactor {
+    fn default() -> Self {
+        Self {
+            headers: DEFAULT_SENSITIVE_HEADERS.iter().map(|name| name.to_string()).collect(),
+            #[cfg(feature = "body-redaction")]
+            body_patterns: Vec::new(),
+        }
+    }
+}
+
+impl Redactor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn redact_header(mut self, name: impl Display) -> Self {
+        self.headers.insert(name.to_string().to_ascii_lowercase());
+        self
+    }
+
+    #[cfg(feature = "body-redaction")]
+    pub fn redact_body_pattern(mut self, pattern: &str) -> Result<Self, regex::Error> {
+        self.body_patterns.push(Regex::new(pattern)?);
+        Ok(self)
+    }
+
+    pub fn is_sensitive_header(&self, name: &str) -> bool {
+        self.headers.contains(&name.to_ascii_lowercase())
+    }
+
+    pub fn request<'a>(&'a self, request: &'a HttpRequest) -> RedactedRequest<'a> {
+        RedactedRequest { redactor: self, request }
+    }
+
+    pub fn response<'a>(&'a self, response: &'a HttpResponse) -> RedactedResponse<'a> {
+        RedactedResponse { redactor: self, response }
+    }
+
+    #[cfg(feature = "body-redaction")]
+    pub fn redact_text<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        for pattern in &self.body_patterns {
+            if !pattern.is_match(&text) {
+                continue;
+            }
+
+            let mut redacted = String::with_capacity(text.len());
+            let mut last = 0;
+            for captures in pattern.captures_iter(&text) {
+                let span = captures.get(1).or_else(|| captures.get(0)).expect("Capture 0 always participates in a match");
+                redacted.push_str(&text[last..span.start()]);
+                redacted.push_str(REDACTED);
+                last = span.end();
+            }
+
+            redacted.push_str(&text[last..]);
+            text = Cow::Owned(redacted);
+        }
+
+        text
+    }
+
+    #[cfg(not(feature = "body-redaction"))]
+    pub fn redact_text<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        Cow::Borrowed(text)
+    }
+
+    fn headers<'a>(&'a self, headers: &'a HeaderMap) -> RedactedHeaders<'a> {
+        RedactedHeaders { redactor: self, headers }
+    }
+
+    pub fn header_value<'a>(&self, key: &str, val: &'a str) -> &'a str {
+        match self.is_sensitive_header(key) {
+            true => REDACTED,
+            false => val,
+        }
+    }
+
+    pub fn raw_head<'a>(&self, head: &'a [u8]) -> Cow<'a, [u8]> {
+        let sensitive_name = |line: &[u8]| line.iter()
+            .position(|&b| b == b':')
+            .is_some_and(|index| self.is_sensitive_header(String::from_utf8_lossy(&line[..index]).trim()));
+
+        if !head.split(|&b| b == b'\n').any(sensitive_name) {
+            return Cow::Borrowed(head);
+        }
+
+        let mut redacted = Vec::with_capacity(head.len());
+        let mut in_sensitive = false;
+        for (index, line) in head.split(|&b| b == b'\n').enumerate() {
+            if index > 0 {
+                redacted.push(b'\n');
+            }
+
+            // Folded continuation lines belong to the previous header's value.
+            let folded = line.starts_with(b" ") || line.starts_with(b"\t");
+            in_sensitive = match folded {
+                true => in_sensitive,
+                false => sensitive_name(line),
+            };
+
+            match (in_sensitive, folded) {
+                (false, _) => redacted.extend_from_slice(line),
+                (true, true) => redacted.push(b' '),
+                (true, false) => {
+                    let colon = line.iter().position(|&b| b == b':').unwrap_or_default();
+                    redacted.extend_from_slice(&line[..=colon]);
+                    redacted.push(b' ');
+                    redacted.extend_from_slice(REDACTED.as_bytes());
+                },
+            }
+
+            if in_sensitive && line.ends_with(b"\r") {
+                redacted.push(b'\r');
+            }
+        }
+
+        Cow::Owned(redacted)
+    }
+
+    fn body_text(&self, body: &Body) -> String {
+        match body.in_memory() {
+            Some(bytes) => self.bytes_text(bytes),
+            None => format!("<{} bytes on disk>", body.len()),
+        }
+    }
+
+    fn bytes_text(&self, bytes: &[u8]) -> String {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => self.redact_text(text).into_owned(),
+            Err(_) => format!("<{} bytes of binary data>", bytes.len()),
+        }
+    }
+}
+
+struct RedactedHeaders<'a> {
+    redactor: &'a Redactor,
+    headers: &'a HeaderMap,
+}
+
+impl Debug for RedactedHeaders<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.headers.iter().map(|(key, val)| (key, self.redactor.header_value(key, val))))
+            .finish()
+    }
+}
+
+pub struct RedactedRequest<'a> {
+    redactor: &'a Redactor,
+    request: &'a HttpRequest,
+}
+
+impl Debug for RedactedRequest<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpRequest")
+            .field("method", &self.request.method())
+            .field("route", self.request.route())
+            .field("version", &self.request.version())
+            .field("headers", &self.redactor.headers(self.request.headers()))
+            .field("body", &self.redactor.body_text(self.request.body()))
+            .finish()
+    }
+}
+
+impl Display for RedactedRequest<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}\r\n", self.request.method(), self.redactor.redact_text(&self.request.route().to_string()), self.request.version())?;
+        for (key, val) in self.request.headers().iter() {
+            write!(f, "{}: {}\r\n", key, self.redactor.header_value(key, val))?;
+        }
+
+        write!(f, "\r\n{}", self.redactor.body_text(self.request.body()))
+    }
+}
+
+pub struct RedactedResponse<'a> {
+    redactor: &'a Redactor,
+    response: &'a HttpResponse,
+}
+
+impl Debug for RedactedResponse<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpResponse")
+            .field("status", &self.response.status())
+            .field("version", &self.response.version())
+            .field("headers", &self.redactor.headers(self.response.headers()))
+            .field("body", &self.redactor.bytes_text(self.response.body()))
+            .finish()
+    }
+}
+
+impl Display for RedactedResponse<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}\r\n", self.response.version(), self.response.status())?;
+        for (key, val) in self.response.headers().iter() {
+            write!(f, "{}: {}\r\n", key, self.redactor.header_value(key, val))?;
+        }
+
+        write!(f, "\r\n{}", self.redactor.bytes_text(self.response.body()))
+    }
+}
@@ -0,0 +1,47 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+#[derive(Debug)]
+pub struct RingBuffer<T> {
+    capacity: usize,
+    items: Mutex<VecDeque<T>>,
+}
+
+impl<T: Clone> RingBuffer<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, items: Mutex::new(VecDeque::with_capacity(capacity)) }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn push(&self, item: T) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut items = self.items.lock().unwrap_or_else(|e| e.into_inner());
+        if items.len() == self.capacity {
+            items.pop_front();
+        }
+
+        items.push_back(item);
+    }
+
+    pub fn snapshot(&self) -> Vec<T> {
+        let items = self.items.lock().unwrap_or_else(|e| e.into_inner());
+        items.iter().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.items.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+}
@@ -0,0 +1,126 @@
+use std::{fmt::Display, str::FromStr, sync::{atomic::{AtomicU64, Ordering}, Mutex, RwLock}, time::SystemTime};
+
+use crate::{access_log::AccessLogEntry, ring::RingBuffer, throttle::TokenBucket};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SampleRate {
+    #[default]
+    Off,
+    OneIn(u64),
+    PerSecond(u64),
+}
+
+impl FromStr for SampleRate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("'{}' is not a valid sample rate", s);
+        let rate = match s.to_ascii_lowercase().as_str() {
+            "off" => return Ok(Self::Off),
+            rate => match rate.strip_suffix("/s") {
+                Some(per_second) => Self::PerSecond(per_second.parse().map_err(|_| invalid())?),
+                None => Self::OneIn(rate.parse().map_err(|_| invalid())?),
+            },
+        };
+
+        match rate {
+            Self::OneIn(0) | Self::PerSecond(0) => Ok(Self::Off),
+            rate => Ok(rate),
+        }
+    }
+}
+
+impl Display for SampleRate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Off => write!(f, "off"),
+            Self::OneIn(n) => write!(f, "1 in {}", n),
+            Self::PerSecond(n) => write!(f, "{}/s", n),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub captured_at: SystemTime,
+    pub entry: AccessLogEntry,
+    pub request: String,
+    pub response: String,
+}
+
+impl Display for Sample {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let since_epoch = self.captured_at.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+        writeln!(f, "[{:.3}] {}", since_epoch.as_secs_f64(), self.entry)?;
+        writeln!(f, "{}", self.request.trim_end())?;
+        writeln!(f)?;
+        write!(f, "{}", self.response.trim_end())
+    }
+}
+
+#[derive(Debug)]
+pub struct RequestSampler {
+    rate: RwLock<SampleRate>,
+    seen: AtomicU64,
+    budget: Mutex<Option<TokenBucket>>,
+    samples: RingBuffer<Sample>,
+}
+
+impl RequestSampler {
+    pub fn new(rate: SampleRate, capacity: usize) -> Self {
+        let sampler = Self {
+            rate: RwLock::new(SampleRate::Off),
+            seen: AtomicU64::new(0),
+            budget: Mutex::new(None),
+            samples: RingBuffer::new(capacity),
+        };
+
+        sampler.set_rate(rate);
+        sampler
+    }
+
+    pub fn rate(&self) -> SampleRate {
+        *self.rate.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn set_rate(&self, rate: SampleRate) {
+        let mut budget = self.budget.lock().unwrap_or_else(|e| e.into_inner());
+        *budget = match rate {
+            SampleRate::PerSecond(n) => Some(TokenBucket::new(n)),
+            SampleRate::Off | SampleRate::OneIn(_) => None,
+        };
+
+        *self.rate.write().unwrap_or_else(|e| e.into_inner()) = rate;
+        self.seen.store(0, Ordering::Relaxed);
+    }
+
+    pub fn should_sample(&self) -> bool {
+        match self.rate() {
+            SampleRate::Off => false,
+            SampleRate::OneIn(n) => self.seen.fetch_add(1, Ordering::Relaxed).is_multiple_of(n),
+            SampleRate::PerSecond(_) => self.budget
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .as_mut()
+                .is_some_and(|budget| budget.try_take(1)),
+        }
+    }
+
+    pub fn record(&self, sample: Sample) {
+        self.samples.push(sample);
+    }
+
+    pub fn samples(&self) -> Vec<Sample> {
+        self.samples.snapshot()
+    }
+
+    pub fn clear(&self) {
+        self.samples.clear();
+    }
+}
+
+impl Default for RequestSampler {
+    fn default() -> Self {
+        Self::new(SampleRate::Off, 32)
+    }
+}
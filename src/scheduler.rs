@@ -0,0 +1,138 @@
+use std::{future::Future, pin::Pin, sync::Arc, time::Duration};
+
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+const MIN_INTERVAL: Duration = Duration::from_millis(1);
+
+type JobFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+type JobFn = Arc<dyn Fn() -> JobFuture + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schedule {
+    Once { delay: Duration },
+    Every { interval: Duration, delay: Duration },
+}
+
+struct Job {
+    name: String,
+    schedule: Schedule,
+    run: JobFn,
+}
+
+#[derive(Default)]
+pub struct Scheduler {
+    jobs: Vec<Job>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self { jobs: Vec::new() }
+    }
+
+    pub fn schedule<F, Fut>(&mut self, name: impl std::fmt::Display, schedule: Schedule, job: F) -> &mut Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let name = name.to_string();
+        let schedule = match schedule {
+            Schedule::Every { interval, delay } if interval < MIN_INTERVAL => {
+                log::warn!("Background job '{}' has an interval of {:?}, using {:?} instead", name, interval, MIN_INTERVAL);
+                Schedule::Every { interval: MIN_INTERVAL, delay }
+            },
+            schedule => schedule,
+        };
+
+        self.jobs.push(Job {
+            name,
+            schedule,
+            run: Arc::new(move || Box::pin(job())),
+        });
+
+        self
+    }
+
+    pub fn every<F, Fut>(&mut self, name: impl std::fmt::Display, interval: Duration, job: F) -> &mut Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.schedule(name, Schedule::Every { interval, delay: Duration::ZERO }, job)
+    }
+
+    pub fn after<F, Fut>(&mut self, name: impl std::fmt::Display, delay: Duration, job: F) -> &mut Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.schedule(name, Schedule::Once { delay }, job)
+    }
+
+    pub fn start(self, shutdown: CancellationToken) -> SchedulerHandle {
+        let tasks = self.jobs
+            .into_iter()
+            .map(|job| tokio::spawn(run_job(job, shutdown.clone())))
+            .collect();
+
+        SchedulerHandle { shutdown, tasks }
+    }
+}
+
+pub struct SchedulerHandle {
+    shutdown: CancellationToken,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl SchedulerHandle {
+    pub fn active_jobs(&self) -> usize {
+        self.tasks.iter().filter(|task| !task.is_finished()).count()
+    }
+
+    pub async fn shutdown(self) {
+        self.shutdown.cancel();
+        for task in self.tasks {
+            if let Err(e) = task.await {
+                log::error!("A background job failed: {}", e);
+            }
+        }
+    }
+}
+
+async fn run_job(job: Job, shutdown: CancellationToken) {
+    let (delay, interval) = match job.schedule {
+        Schedule::Once { delay } => (delay, None),
+        Schedule::Every { interval, delay } => (delay, Some(interval)),
+    };
+
+    tokio::select! {
+        _ = shutdown.cancelled() => return,
+        _ = tokio::time::sleep(delay) => (),
+    }
+
+    let Some(interval) = interval else {
+        tokio::select! {
+            _ = shutdown.cancelled() => log::debug!("Background job '{}' cancelled", job.name),
+            _ = (job.run)() => (),
+        }
+
+        return;
+    };
+
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            _ = ticker.tick() => (),
+        }
+
+        tokio::select! {
+            _ = shutdown.cancelled() => {
+                log::debug!("Background job '{}' cancelled", job.name);
+                break;
+            },
+            _ = (job.run)() => (),
+        }
+    }
+}
@@ -0,0 +1,1121 @@
+use std::{net::{IpAddr, Ipv4Addr, SocketAddr}, sync::Arc, time::{Duration, Instant, SystemTime}};
+
+use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::TcpListener, sync::mpsc::{self, error::TryRecvError}};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+
+use crate::{
+    access_log::{AccessLogEntry, AccessLogLevel, AccessLogRules},
+    admission::{AdmissionControl, Priority},
+    error_log::{ErrorLog, ErrorRecord},
+    errors::{ConnectionError, ConnectionErrorKind, Error},
+    faults::{Fault, FaultInjector},
+    hooks::ResponseHooks,
+    memory::MemoryAccount,
+    models::{Body, BodyBuffer, HttpMethod, HttpRequest, HttpResponse, HttpStatusCode, HttpVersion, MethodOverride, parse_chunk_size, ParseRequestErr, RequestParser, Status},
+    penalty::PenaltyBox,
+    redact::Redactor,
+    router::Router,
+    sampling::{RequestSampler, Sample},
+    stats::ListenerStats,
+    streaming::{Chunk, ResponseStream},
+    throttle::TokenBucket,
+    trace::{format_request, format_response, RequestTracer},
+    transport::Transport,
+};
+#[cfg(feature = "sni")]
+use tokio::net::TcpStream;
+#[cfg(feature = "sni")]
+use crate::sni::{read_server_name, SniError, SniRouter};
+#[cfg(feature = "geoip")]
+use crate::geoip::GeoIp;
+
+const READINESS_ROUTE: &str = "/readyz";
+pub const DEFAULT_HEADER_TIMEOUT: Duration = Duration::from_secs(10);
+pub const DEFAULT_BODY_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_MAX_HEAD_SIZE: usize = 64 * 1024;
+pub const DEFAULT_MAX_BODY_SIZE: u64 = 16 * 1024 * 1024;
+pub const DEFAULT_BODY_SPILL_THRESHOLD: usize = 1024 * 1024;
+pub const DEFAULT_KEEP_ALIVE_TIMEOUT: Duration = Duration::from_secs(5);
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+const IO_CHUNK_SIZE: usize = 4096;
+const SAMPLE_BODY_LIMIT: usize = 256;
+const ERROR_CONTEXT_LIMIT: usize = 200;
+const MAX_CHUNK_LINE_SIZE: usize = 4096;
+const MAX_TRAILER_SIZE: usize = 8 * 1024;
+#[cfg(feature = "sni")]
+const SNI_READ_TIMEOUT: Duration = Duration::from_secs(10);
+#[cfg(feature = "sni")]
+const MAX_CLIENT_HELLO_SIZE: usize = 16 * 1024;
+const LINGER_TIMEOUT: Duration = Duration::from_secs(2);
+const LINGER_MAX_BYTES: usize = 64 * 1024;
+const CONTINUE_RESPONSE: &[u8] = b"HTTP/1.1 100 Continue\r\n\r\n";
+const UNKNOWN_PEER: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0);
+
+#[derive(Debug, Clone, Copy)]
+pub struct ListenerConfig {
+    pub parser: RequestParser,
+    pub method_override: MethodOverride,
+    pub read_limit: Option<u64>,
+    pub write_limit: Option<u64>,
+    pub body_spill_threshold: usize,
+    pub slow_request_threshold: Option<Duration>,
+    pub bad_request_body: bool,
+    pub log_parse_error_details: bool,
+    pub legacy_clients: bool,
+    pub header_timeout: Duration,
+    pub body_timeout: Duration,
+    pub max_head_size: usize,
+    pub max_body_size: u64,
+    pub keep_alive_timeout: Option<Duration>,
+    pub max_requests_per_connection: Option<usize>,
+    pub shutdown_timeout: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct ServerContext {
+    pub stats: Arc<ListenerStats>,
+    pub faults: Arc<FaultInjector>,
+    pub redactor: Arc<Redactor>,
+    pub tracer: Arc<RequestTracer>,
+    pub penalties: Arc<PenaltyBox>,
+    pub hooks: Arc<ResponseHooks>,
+    pub sampler: Arc<RequestSampler>,
+    pub errors: Arc<ErrorLog>,
+    pub access_log: Arc<AccessLogRules>,
+    pub router: Arc<Router>,
+    pub body_memory: Arc<MemoryAccount>,
+    pub admission: Arc<AdmissionControl>,
+    #[cfg(feature = "geoip")]
+    pub geoip: Arc<GeoIp>,
+    pub shutdown: CancellationToken,
+}
+
+impl Default for ListenerConfig {
+    fn default() -> Self {
+        Self {
+            parser: RequestParser::default(),
+            method_override: MethodOverride::default(),
+            read_limit: None,
+            write_limit: None,
+            body_spill_threshold: DEFAULT_BODY_SPILL_THRESHOLD,
+            slow_request_threshold: None,
+            bad_request_body: true,
+            log_parse_error_details: true,
+            legacy_clients: false,
+            header_timeout: DEFAULT_HEADER_TIMEOUT,
+            body_timeout: DEFAULT_BODY_TIMEOUT,
+            max_head_size: DEFAULT_MAX_HEAD_SIZE,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            keep_alive_timeout: Some(DEFAULT_KEEP_ALIVE_TIMEOUT),
+            max_requests_per_connection: None,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+        }
+    }
+}
+
+impl Default for ServerContext {
+    fn default() -> Self {
+        Self {
+            stats: Arc::default(),
+            faults: Arc::default(),
+            redactor: Arc::default(),
+            tracer: Arc::default(),
+            penalties: Arc::default(),
+            hooks: Arc::default(),
+            sampler: Arc::default(),
+            errors: Arc::default(),
+            access_log: Arc::default(),
+            router: Arc::default(),
+            body_memory: Arc::new(MemoryAccount::new("request bodies", None)),
+            admission: Arc::default(),
+            #[cfg(feature = "geoip")]
+            geoip: Arc::default(),
+            shutdown: CancellationToken::new(),
+        }
+    }
+}
+
+impl ServerContext {
+    pub fn begin_shutdown(&self) {
+        self.stats.start_draining();
+        self.shutdown.cancel();
+    }
+}
+
+struct Message {
+    head: Vec<u8>,
+    request: Result<HttpRequest, ConnectionError>,
+    rejection: Option<HttpResponse>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Framing {
+    Chunked,
+    Sized(u64),
+    Close,
+}
+
+#[derive(Debug, Clone)]
+pub struct Server {
+    addr: String,
+    config: ListenerConfig,
+    context: ServerContext,
+    #[cfg(feature = "sni")]
+    sni_router: Option<Arc<SniRouter>>,
+}
+
+impl Server {
+    pub fn new(addr: impl Into<String>) -> Self {
+        Self {
+            addr: addr.into(),
+            config: ListenerConfig::default(),
+            context: ServerContext::default(),
+            #[cfg(feature = "sni")]
+            sni_router: None,
+        }
+    }
+
+    pub fn with_config(mut self, config: ListenerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn with_context(mut self, context: ServerContext) -> Self {
+        self.context = context;
+        self
+    }
+
+    #[cfg(feature = "sni")]
+    pub fn with_sni_router(mut self, router: SniRouter) -> Self {
+        self.sni_router = Some(Arc::new(router));
+        self
+    }
+
+    pub fn addr(&self) -> &str {
+        &self.addr
+    }
+
+    pub fn config(&self) -> &ListenerConfig {
+        &self.config
+    }
+
+    pub fn context(&self) -> &ServerContext {
+        &self.context
+    }
+
+    pub async fn serve_connection<T: Transport>(&self, stream: T) -> Result<(), ConnectionError> {
+        let addr = stream.peer_addr().unwrap_or(UNKNOWN_PEER);
+        handle_connection_wrapper(stream, addr, self.config, self.context.clone()).await
+    }
+
+    pub async fn run(self) -> Result<(), Error> {
+        #[cfg(feature = "sni")]
+        if let Some(router) = self.sni_router {
+            return run_sni_router(self.addr, self.config, router, self.context).await;
+        }
+
+        run_server(self.addr, self.config, self.context).await
+    }
+}
+
+async fn run_server(addr: String, config: ListenerConfig, context: ServerContext) -> Result<(), Error> {
+    let listener = TcpListener::bind(&addr)
+        .await
+        .map_err(|e| Error::Bind(addr, e))?;
+
+    let connections = TaskTracker::new();
+    loop {
+        let (stream, addr) = tokio::select! {
+            accepted = listener.accept() => accepted.map_err(Error::Accept)?,
+            _ = context.shutdown.cancelled() => break,
+        };
+
+        if context.penalties.is_penalized(addr.ip()) {
+            println!("Refused connection from penalized client {}", addr);
+            continue;
+        }
+
+        connections.spawn(handle_connection_wrapper(stream, addr, config, context.clone()));
+    }
+
+    drop(listener);
+    wait_for_connections(connections, config.shutdown_timeout).await;
+    Ok(())
+}
+
+#[cfg(feature = "sni")]
+async fn run_sni_router(addr: String, config: ListenerConfig, router: Arc<SniRouter>, context: ServerContext) -> Result<(), Error> {
+    let listener = TcpListener::bind(&addr)
+        .await
+        .map_err(|e| Error::Bind(addr, e))?;
+
+    let connections = TaskTracker::new();
+    loop {
+        let (stream, addr) = tokio::select! {
+            accepted = listener.accept() => accepted.map_err(Error::Accept)?,
+            _ = context.shutdown.cancelled() => break,
+        };
+
+        if context.penalties.is_penalized(addr.ip()) {
+            println!("Refused connection from penalized client {}", addr);
+            continue;
+        }
+
+        let router = router.clone();
+        let context = context.clone();
+        connections.spawn(async move {
+            if let Err(e) = route_tls_connection(stream, addr, &router, &context).await {
+                context.stats.record_error(e.kind());
+                context.errors.record(ErrorRecord::new(addr, &e, None));
+                if let ConnectionError::Sni(SniError::NotTls | SniError::Malformed(_)) = e {
+                    record_offense(&context.penalties, addr);
+                }
+
+                log::error!("Connection with {} failed ({}): {}", addr, e.kind(), e);
+            }
+        });
+    }
+
+    drop(listener);
+    wait_for_connections(connections, config.shutdown_timeout).await;
+    Ok(())
+}
+
+async fn wait_for_connections(connections: TaskTracker, timeout: Duration) {
+    connections.close();
+    if !connections.is_empty() {
+        println!("Waiting for {} open connections to finish", connections.len());
+    }
+
+    if tokio::time::timeout(timeout, connections.wait()).await.is_err() {
+        log::warn!("Shutting down with {} connections still open", connections.len());
+    }
+}
+
+#[cfg(feature = "sni")]
+async fn route_tls_connection(mut stream: TcpStream, addr: SocketAddr, router: &SniRouter, context: &ServerContext) -> Result<(), ConnectionError> {
+    let mut hello = Vec::new();
+    let server_name = loop {
+        if hello.len() >= MAX_CLIENT_HELLO_SIZE {
+            return Err(ConnectionError::LimitExceeded(format!("ClientHello is larger than {} bytes", MAX_CLIENT_HELLO_SIZE)));
+        }
+
+        let mut temp_buffer = [0_u8; IO_CHUNK_SIZE];
+        let count = tokio::time::timeout(SNI_READ_TIMEOUT, stream.read(&mut temp_buffer))
+            .await
+            .map_err(|_| ConnectionError::Timeout)??;
+
+        if count == 0 {
+            return Err(ConnectionError::ResetByPeer(std::io::ErrorKind::UnexpectedEof.into()));
+        }
+
+        hello.extend_from_slice(&temp_buffer[..count]);
+        match read_server_name(&hello) {
+            Ok(server_name) => break server_name,
+            Err(SniError::Incomplete) => continue,
+            Err(e) => return Err(ConnectionError::Sni(e)),
+        }
+    };
+
+    let backend = router.backend(server_name.as_deref())
+        .ok_or_else(|| ConnectionError::Sni(SniError::NoRoute(server_name.clone().unwrap_or_default())))?;
+
+    println!("Routing {} ({}) to {}", addr, server_name.as_deref().unwrap_or("no server name"), backend);
+
+    let _guard = context.stats.track_request();
+    let mut upstream = TcpStream::connect(backend).await?;
+    upstream.write_all(&hello).await?;
+
+    let (bytes_read, bytes_written) = tokio::io::copy_bidirectional(&mut stream, &mut upstream).await?;
+    let bytes_read = hello.len() as u64 + bytes_read;
+    context.stats.record_transfer(bytes_read, bytes_written);
+    println!("Connection with {} closed after routing to {} (in={} out={})", addr, backend, bytes_read, bytes_written);
+
+    Ok(())
+}
+
+async fn handle_connection_wrapper<T: Transport>(stream: T, addr: SocketAddr, config: ListenerConfig, context: ServerContext) -> Result<(), ConnectionError> {
+    let stats = context.stats.clone();
+    let penalties = context.penalties.clone();
+    let errors = context.errors.clone();
+    let result = handle_connection(stream, addr, config, context).await;
+    if let Err(e) = &result {
+        stats.record_error(e.kind());
+        errors.record(ErrorRecord::new(addr, e, None));
+        if e.kind() == ConnectionErrorKind::LimitExceeded {
+            record_offense(&penalties, addr);
+        }
+
+        log::error!("Connection with {} failed ({}): {}", addr, e.kind(), e);
+    }
+
+    result
+}
+
+async fn handle_connection<T: Transport>(mut stream: T, addr: SocketAddr, config: ListenerConfig, context: ServerContext) -> Result<(), ConnectionError> {
+    println!("Connection established with {}", addr);
+
+    let mut read_throttle = config.read_limit.map(TokenBucket::new);
+    let mut write_throttle = config.write_limit.map(TokenBucket::new);
+    let mut buffer = Vec::new();
+    let mut served = 0;
+
+    loop {
+        if served > 0 && buffer.is_empty() {
+            let idle_deadline = tokio::time::Instant::now() + config.keep_alive_timeout.unwrap_or_default();
+            let read = tokio::select! {
+                read = read_some(&mut stream, &mut buffer, idle_deadline, read_throttle.as_mut()) => read,
+                _ = context.shutdown.cancelled() => break,
+            };
+
+            match read {
+                Ok(0) | Err(ConnectionError::Timeout | ConnectionError::ResetByPeer(_)) => break,
+                Ok(_) => {},
+                Err(e) => return Err(e),
+            }
+        }
+
+        served += 1;
+        let last = context.shutdown.is_cancelled() || config.max_requests_per_connection.is_some_and(|max| served >= max);
+        let keep_alive = handle_request(&mut stream, addr, &config, &context, &mut buffer, read_throttle.as_mut(), write_throttle.as_mut(), last).await?;
+        if !keep_alive {
+            break;
+        }
+    }
+
+    println!("Connection with {} closed", addr);
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn handle_request<T: Transport>(stream: &mut T, addr: SocketAddr, config: &ListenerConfig, context: &ServerContext, buffer: &mut Vec<u8>, read_throttle: Option<&mut TokenBucket>, mut write_throttle: Option<&mut TokenBucket>, last: bool) -> Result<bool, ConnectionError> {
+    let read_start = Instant::now();
+    let Message { head, request: model, rejection } = match read_message(stream, addr, config, context, buffer, read_throttle, write_throttle.as_deref_mut()).await {
+        Ok(message) => message,
+        Err(e) => {
+            let status = match &e {
+                ConnectionError::Timeout => HttpStatusCode::RequestTimeout,
+                ConnectionError::HeadTooLarge(_) => HttpStatusCode::RequestHeaderFieldsTooLarge,
+                ConnectionError::BodyTooLarge(_) => HttpStatusCode::ContentTooLarge,
+                _ => return Err(e),
+            };
+
+            write_all(stream, &closing_response(status).to_bytes(), write_throttle.as_deref_mut()).await?;
+            linger_close(stream).await;
+            return Err(e);
+        },
+    };
+    let bytes_read = head.len() as u64 + model.as_ref().map_or(0, |request| request.body().len());
+    let read_time = read_start.elapsed();
+
+    let mut model = model.map(|mut request| {
+        request.apply_method_override(config.method_override);
+        if config.legacy_clients && request.host().is_none() {
+            synthesize_host(&mut request, stream);
+        }
+
+        request
+    });
+
+    match &model {
+        Ok(request) => if let Some(trace) = context.tracer.trace(request, &context.redactor) {
+            print!("{}", trace);
+        },
+        Err(e) => {
+            log::debug!("Failed to parse the request from {}: {:?}", addr, e);
+            if let Some(trace) = context.tracer.trace_raw(&head, &context.redactor) {
+                print!("{}", trace);
+            }
+        },
+    }
+
+    if let Err(e) = &model {
+        context.stats.record_error(e.kind());
+        context.errors.record(ErrorRecord::new(addr, e, Some(request_context(&head))));
+        record_offense(&context.penalties, addr);
+        if config.log_parse_error_details {
+            log::error!("Connection with {} failed ({}): {}", addr, e.kind(), e);
+        } else {
+            log::error!("Connection with {} failed ({})", addr, e.kind());
+        }
+    }
+
+    let sample_request = context.sampler.should_sample().then(|| match &model {
+        Ok(request) => format_request(request, &context.redactor, true, SAMPLE_BODY_LIMIT),
+        Err(e) => format!("{} ({} bytes)", e, head.len()),
+    });
+
+    let _guard = context.stats.track_request();
+    let handle_start = Instant::now();
+    let mut entry = AccessLogEntry::new(addr, model.as_ref().ok());
+    entry.bytes_read = bytes_read;
+    entry.read_time = read_time;
+
+    let rejected = rejection.is_some();
+    let priority = match &model {
+        Ok(request) if is_readiness_probe(request) => Priority::High,
+        Ok(request) => context.router.priority(request),
+        Err(_) => Priority::Normal,
+    };
+
+    let handler = async {
+        let permit = context.admission.admit(priority).await;
+        let response = match (&mut model, rejection) {
+            _ if permit.is_none() => overloaded_response(),
+            (Ok(_), Some(rejection)) => rejection,
+            (Ok(request), None) if is_readiness_probe(request) => readiness_response(&context.stats),
+            (Ok(request), None) => context.router.handle(request).await,
+            (Err(e), _) => parse_error_response(e, config.bad_request_body),
+        };
+
+        if let Some(delay) = context.faults.sample_delay() {
+            tokio::time::sleep(delay).await;
+        }
+
+        (response, permit)
+    };
+
+    let mut half_closed = false;
+    let (mut response, _permit) = {
+        let mut handler = std::pin::pin!(handler);
+        loop {
+            tokio::select! {
+                response = &mut handler => break response,
+                e = stream.disconnected(), if !half_closed => match is_half_close(&e) {
+                    true => half_closed = true,
+                    false => {
+                        println!("Connection with {} closed by the client before the response was ready", addr);
+                        return Err(ConnectionError::ResetByPeer(e));
+                    },
+                },
+            }
+        }
+    };
+
+    context.hooks.apply(model.as_ref().ok(), &mut response);
+    response.fill_content_length();
+
+    // HTTP/1.0 clients can't decode chunked bodies, so an unsized stream ends when the connection closes instead.
+    let close_delimited = model.as_ref().is_ok_and(|request| request.version() == HttpVersion::new(1, 0))
+        && response.extensions().get::<ResponseStream>().is_some_and(|body_stream| body_stream.length().is_none());
+    if close_delimited {
+        response.headers_mut().remove("Transfer-Encoding");
+    }
+
+    let keep_alive = config.keep_alive_timeout.is_some()
+        && !last
+        && !rejected
+        && !close_delimited
+        && !half_closed
+        && model.as_ref().is_ok_and(wants_keep_alive)
+        && !has_connection_token(response.headers().get("Connection"), "close");
+
+    if !keep_alive {
+        response.headers_mut().insert("Connection", "close");
+    } else if model.as_ref().is_ok_and(|request| request.version() == HttpVersion::new(1, 0)) {
+        response.headers_mut().insert("Connection", "keep-alive");
+    }
+
+    let sample_response = sample_request.as_ref().map(|_| format_response(&response, &context.redactor, true, SAMPLE_BODY_LIMIT));
+    // Responses to HEAD keep their Content-Length but never carry a body.
+    let head_only = model.as_ref().is_ok_and(|request| request.method() == HttpMethod::HEAD);
+    let encode = |response: &HttpResponse| match head_only {
+        true => response.head_bytes(),
+        false => response.to_bytes(),
+    };
+
+    let mut body_stream = response.extensions().get::<ResponseStream>()
+        .and_then(|body_stream| {
+            let framing = match body_stream.length() {
+                Some(length) => Framing::Sized(length),
+                None if close_delimited => Framing::Close,
+                None => Framing::Chunked,
+            };
+
+            Some((body_stream.take()?, framing))
+        })
+        .filter(|_| !head_only);
+
+    let (status, response) = match context.faults.sample_fault() {
+        None => (response.status(), encode(&response)),
+        Some(Fault::Error) => {
+            body_stream = None;
+            let mut response = HttpResponse::new(HttpStatusCode::InternalServerError, "Injected fault");
+            context.hooks.apply(model.as_ref().ok(), &mut response);
+            response.fill_content_length();
+            (response.status(), encode(&response))
+        },
+        Some(Fault::Reset) => {
+            stream.reset()?;
+            println!("Connection with {} reset by fault injection", addr);
+            return Ok(false);
+        },
+        Some(Fault::Truncate) => {
+            body_stream = None;
+            let mut bytes = encode(&response);
+            bytes.truncate(bytes.len() / 2);
+            (response.status(), bytes)
+        },
+    };
+
+    entry.status = Some(status);
+    entry.handle_time = handle_start.elapsed();
+
+    let write_start = Instant::now();
+    entry.bytes_written = match body_stream {
+        Some((chunks, framing)) => write_stream(stream, response, chunks, framing, write_throttle).await?,
+        None => write_all(stream, &response, write_throttle).await?,
+    };
+    entry.write_time = write_start.elapsed();
+
+    context.stats.record_transfer(entry.bytes_read, entry.bytes_written);
+    let log_level = match &model {
+        Ok(request) => context.access_log.level(&request.route().normalized_path()),
+        Err(_) => AccessLogLevel::Full,
+    };
+
+    if log_level.allows(entry.status) {
+        println!("{}", entry);
+    }
+
+    if let (Some(request), Some(response)) = (sample_request, sample_response) {
+        context.sampler.record(Sample { captured_at: SystemTime::now(), entry: entry.clone(), request, response });
+    }
+
+    if config.slow_request_threshold.is_some_and(|threshold| entry.total_time() >= threshold) {
+        context.stats.record_slow_request();
+        println!("Slow request ({:.3}ms): {}", entry.total_time().as_secs_f64() * 1000.0, entry);
+    }
+
+    if !keep_alive {
+        linger_close(stream).await;
+    }
+
+    Ok(keep_alive)
+}
+
+fn request_context(head: &[u8]) -> String {
+    let line = head.split(|&b| b == b'\n').next().unwrap_or_default();
+    String::from_utf8_lossy(line.trim_ascii())
+        .chars()
+        .take(ERROR_CONTEXT_LIMIT)
+        .map(|c| if c.is_control() { '.' } else { c })
+        .collect()
+}
+
+fn record_offense(penalties: &PenaltyBox, addr: SocketAddr) {
+    if penalties.record_offense(addr.ip()) {
+        println!("Client {} is penalized after repeated rejected requests", addr.ip());
+    }
+}
+
+async fn linger_close<T: Transport>(stream: &mut T) {
+    if let Err(e) = stream.shutdown().await {
+        log::warn!("Failed to shut down the write half: {}", e);
+        return;
+    }
+
+    let drain = async {
+        let mut buffer = [0_u8; IO_CHUNK_SIZE];
+        let mut drained = 0;
+        while drained < LINGER_MAX_BYTES {
+            match stream.read(&mut buffer).await {
+                Ok(0) | Err(_) => break,
+                Ok(count) => drained += count,
+            }
+        }
+    };
+
+    let _ = tokio::time::timeout(LINGER_TIMEOUT, drain).await;
+}
+
+fn synthesize_host<T: Transport>(request: &mut HttpRequest, stream: &T) {
+    match stream.local_addr() {
+        Ok(addr) => { request.headers_mut().insert("Host", addr); },
+        Err(e) => log::warn!("Failed to synthesize a Host header: {}", e),
+    }
+}
+
+fn wants_keep_alive(request: &HttpRequest) -> bool {
+    let connection = request.headers().get("Connection");
+    match request.version() == HttpVersion::new(1, 0) {
+        true => has_connection_token(connection, "keep-alive"),
+        false => !has_connection_token(connection, "close"),
+    }
+}
+
+fn has_connection_token(connection: Option<&str>, token: &str) -> bool {
+    connection.is_some_and(|val| val.split(',').any(|option| option.trim().eq_ignore_ascii_case(token)))
+}
+
+fn is_readiness_probe(request: &HttpRequest) -> bool {
+    matches!(request.method(), HttpMethod::GET | HttpMethod::HEAD) && request.route().normalized_path() == READINESS_ROUTE
+}
+
+fn parse_error_response(e: &ConnectionError, with_body: bool) -> HttpResponse {
+    let status = match e {
+        ConnectionError::Parse(ParseRequestErr::TooManyHeaders(_)) => HttpStatusCode::RequestHeaderFieldsTooLarge,
+        _ => HttpStatusCode::BadRequest,
+    };
+
+    let body = match with_body {
+        true => status.get_readable_name(),
+        false => "",
+    };
+
+    HttpResponse::builder()
+        .status(status)
+        .header("Connection", "close")
+        .body(body)
+}
+
+fn closing_response(status: HttpStatusCode) -> HttpResponse {
+    let mut response = HttpResponse::builder()
+        .status(status)
+        .header("Connection", "close")
+        .body(status.get_readable_name());
+
+    response.fill_content_length();
+    response
+}
+
+fn overloaded_response() -> HttpResponse {
+    HttpResponse::builder()
+        .status(HttpStatusCode::ServiceUnavailable)
+        .header("Retry-After", 1)
+        .body(HttpStatusCode::ServiceUnavailable.get_readable_name())
+}
+
+fn readiness_response(stats: &ListenerStats) -> HttpResponse {
+    if stats.is_ready() {
+        HttpResponse::new(HttpStatusCode::OK, "ready")
+    } else {
+        HttpResponse::new(HttpStatusCode::ServiceUnavailable, "draining")
+    }
+}
+
+#[cfg_attr(not(feature = "geoip"), allow(unused_variables))]
+async fn read_message<T: Transport>(stream: &mut T, addr: SocketAddr, config: &ListenerConfig, context: &ServerContext, buffer: &mut Vec<u8>, mut throttle: Option<&mut TokenBucket>, write_throttle: Option<&mut TokenBucket>) -> Result<Message, ConnectionError> {
+    let head = read_head(stream, config, buffer, throttle.as_deref_mut()).await?;
+    let mut request = match config.parser.parse_request_head(&head) {
+        Ok(request) => request,
+        Err(e) => return Ok(Message { head, request: Err(e.into()), rejection: None }),
+    };
+
+    #[cfg(feature = "geoip")]
+    context.geoip.tag(&mut request, addr.ip());
+
+    let framing = match (request.headers().get_combined("Transfer-Encoding"), request.headers().content_length()) {
+        (Some(coding), None) if coding.trim().eq_ignore_ascii_case("chunked") => None,
+        (Some(coding), _) => {
+            let e = ParseRequestErr::InvalidHeader(format!("Transfer-Encoding: {}", coding));
+            return Ok(Message { head, request: Err(e.into()), rejection: None });
+        },
+        (None, Some(Ok(length))) => Some(length),
+        (None, Some(Err(e))) => return Ok(Message { head, request: Err(e.into()), rejection: None }),
+        (None, None) => Some(0),
+    };
+
+    if framing.is_some_and(|length| length > config.max_body_size) {
+        return Err(ConnectionError::BodyTooLarge(config.max_body_size));
+    }
+
+    if framing != Some(0) && expects_continue(&request) {
+        if let Some(rejection) = context.router.check_head(&mut request) {
+            return Ok(Message { head, request: Ok(request), rejection: Some(rejection) });
+        }
+
+        if buffer.is_empty() {
+            write_all(stream, CONTINUE_RESPONSE, write_throttle).await?;
+        }
+    }
+
+    let body = match framing {
+        Some(length) => read_body(stream, config, &context.body_memory, buffer, length, throttle).await,
+        None => read_chunked_body(stream, config, &context.body_memory, buffer, throttle).await,
+    };
+
+    match body {
+        Ok(body) => {
+            request.set_body(body);
+            Ok(Message { head, request: Ok(request), rejection: None })
+        },
+        Err(e @ ConnectionError::Parse(_)) => Ok(Message { head, request: Err(e), rejection: None }),
+        Err(e) => Err(e),
+    }
+}
+
+fn expects_continue(request: &HttpRequest) -> bool {
+    request.version() != HttpVersion::new(1, 0)
+        && request.headers().get("Expect").is_some_and(|expect| expect.trim().eq_ignore_ascii_case("100-continue"))
+}
+
+async fn read_head<T: Transport>(stream: &mut T, config: &ListenerConfig, buffer: &mut Vec<u8>, mut throttle: Option<&mut TokenBucket>) -> Result<Vec<u8>, ConnectionError> {
+    let header_deadline = tokio::time::Instant::now() + config.header_timeout;
+    let mut scanned = 0;
+    loop {
+        // A head can only complete or fail at a line ending, so reads that didn't finish a line need no rescan.
+        if buffer[scanned..].contains(&b'\n') {
+            match config.parser.scan_head(buffer) {
+                Ok(Status::Complete(len)) if len > config.max_head_size => return Err(ConnectionError::HeadTooLarge(config.max_head_size)),
+                Ok(Status::Complete(len)) => return Ok(buffer.drain(..len).collect()),
+                Ok(Status::Partial) => {},
+                Err(_) => return Ok(std::mem::take(buffer)),
+            }
+        }
+
+        if buffer.len() > config.max_head_size {
+            return Err(ConnectionError::HeadTooLarge(config.max_head_size));
+        }
+
+        scanned = buffer.len();
+        if read_some(stream, buffer, header_deadline, throttle.as_deref_mut()).await? == 0 {
+            return Ok(std::mem::take(buffer));
+        }
+    }
+}
+
+async fn read_body<T: Transport>(stream: &mut T, config: &ListenerConfig, body_memory: &Arc<MemoryAccount>, buffer: &mut Vec<u8>, length: u64, throttle: Option<&mut TokenBucket>) -> Result<Body, ConnectionError> {
+    if length == 0 {
+        return Ok(Body::empty());
+    }
+
+    let mut body = BodyBuffer::new(config.body_spill_threshold).with_account(body_memory);
+    copy_body(stream, config, &mut body, buffer, length, throttle).await?;
+    Ok(body.finish().await?)
+}
+
+async fn read_chunked_body<T: Transport>(stream: &mut T, config: &ListenerConfig, body_memory: &Arc<MemoryAccount>, buffer: &mut Vec<u8>, mut throttle: Option<&mut TokenBucket>) -> Result<Body, ConnectionError> {
+    let mut body = BodyBuffer::new(config.body_spill_threshold).with_account(body_memory);
+    let mut total: u64 = 0;
+    loop {
+        let line = read_line(stream, config, buffer, MAX_CHUNK_LINE_SIZE, throttle.as_deref_mut()).await?;
+        let size_line = std::str::from_utf8(&line).map_err(ParseRequestErr::from)?;
+        let size = parse_chunk_size(size_line)?;
+        if size == 0 {
+            break;
+        }
+
+        total = total.saturating_add(size);
+        if total > config.max_body_size {
+            return Err(ConnectionError::BodyTooLarge(config.max_body_size));
+        }
+
+        copy_body(stream, config, &mut body, buffer, size, throttle.as_deref_mut()).await?;
+        let line = read_line(stream, config, buffer, MAX_CHUNK_LINE_SIZE, throttle.as_deref_mut()).await?;
+        if !line.is_empty() {
+            return Err(ParseRequestErr::InvalidChunk(String::from_utf8_lossy(&line).into_owned()).into());
+        }
+    }
+
+    let mut trailers = 0;
+    loop {
+        let line = read_line(stream, config, buffer, MAX_TRAILER_SIZE.saturating_sub(trailers), throttle.as_deref_mut()).await?;
+        if line.is_empty() {
+            return Ok(body.finish().await?);
+        }
+
+        trailers += line.len() + 2;
+        if trailers > MAX_TRAILER_SIZE {
+            return Err(ConnectionError::LimitExceeded(format!("Trailers are longer than {} bytes", MAX_TRAILER_SIZE)));
+        }
+    }
+}
+
+async fn copy_body<T: Transport>(stream: &mut T, config: &ListenerConfig, body: &mut BodyBuffer, buffer: &mut Vec<u8>, mut remaining: u64, mut throttle: Option<&mut TokenBucket>) -> Result<(), ConnectionError> {
+    loop {
+        let count = remaining.min(buffer.len() as u64) as usize;
+        body.write(&buffer[..count]).await?;
+        buffer.drain(..count);
+        remaining -= count as u64;
+
+        if remaining == 0 {
+            return Ok(());
+        }
+
+        read_more(stream, config, buffer, throttle.as_deref_mut()).await?;
+    }
+}
+
+async fn read_line<T: Transport>(stream: &mut T, config: &ListenerConfig, buffer: &mut Vec<u8>, limit: usize, mut throttle: Option<&mut TokenBucket>) -> Result<Vec<u8>, ConnectionError> {
+    let mut scanned = 0;
+    loop {
+        if let Some(index) = buffer[scanned..].windows(2).position(|w| w == b"\r\n") {
+            if scanned + index > limit {
+                return Err(ConnectionError::LimitExceeded(format!("Chunk metadata is longer than {} bytes", limit)));
+            }
+
+            let mut line: Vec<u8> = buffer.drain(..scanned + index + 2).collect();
+            line.truncate(scanned + index);
+            return Ok(line);
+        }
+
+        if buffer.len() > limit {
+            return Err(ConnectionError::LimitExceeded(format!("Chunk metadata is longer than {} bytes", limit)));
+        }
+
+        scanned = buffer.len().saturating_sub(1);
+        read_more(stream, config, buffer, throttle.as_deref_mut()).await?;
+    }
+}
+
+async fn read_more<T: Transport>(stream: &mut T, config: &ListenerConfig, buffer: &mut Vec<u8>, throttle: Option<&mut TokenBucket>) -> Result<(), ConnectionError> {
+    // Once the head has been read the client gets no 408, so a stalled body just closes the connection.
+    let deadline = tokio::time::Instant::now() + config.body_timeout;
+    let count = match read_some(stream, buffer, deadline, throttle).await {
+        Err(ConnectionError::Timeout) => return Err(ConnectionError::BodyTimeout),
+        count => count?,
+    };
+
+    match count {
+        0 => Err(ConnectionError::ResetByPeer(std::io::ErrorKind::UnexpectedEof.into())),
+        _ => Ok(()),
+    }
+}
+
+async fn read_some<T: Transport>(stream: &mut T, buffer: &mut Vec<u8>, deadline: tokio::time::Instant, throttle: Option<&mut TokenBucket>) -> Result<usize, ConnectionError> {
+    let mut temp_buffer = [0_u8; IO_CHUNK_SIZE];
+    let count = tokio::time::timeout_at(deadline, stream.read(&mut temp_buffer))
+        .await
+        .map_err(|_| ConnectionError::Timeout)??;
+
+    buffer.extend_from_slice(&temp_buffer[..count]);
+    if let Some(throttle) = throttle {
+        throttle.take(count as u64).await;
+    }
+
+    Ok(count)
+}
+
+async fn write_stream<T: Transport>(stream: &mut T, head: Vec<u8>, mut chunks: mpsc::Receiver<Chunk>, mut framing: Framing, mut throttle: Option<&mut TokenBucket>) -> Result<u64, ConnectionError> {
+    let mut written = 0;
+    let mut pending = head;
+    let mut half_closed = false;
+    let mut flushed = Vec::new();
+    loop {
+        let finished = loop {
+            match chunks.try_recv() {
+                Ok(chunk) => {
+                    write_frame(&mut pending, &chunk.bytes, &mut framing);
+                    flushed.push(chunk.flushed);
+                },
+                Err(TryRecvError::Empty) => break false,
+                Err(TryRecvError::Disconnected) => break true,
+            }
+        };
+
+        if finished && matches!(framing, Framing::Chunked) {
+            pending.extend_from_slice(b"0\r\n\r\n");
+        }
+
+        if !pending.is_empty() {
+            written += write_all(stream, &pending, throttle.as_deref_mut()).await?;
+            pending.clear();
+            flushed.drain(..).for_each(|flushed| { let _ = flushed.send(()); });
+        }
+
+        if finished {
+            return match framing {
+                Framing::Sized(short @ 1..) => Err(ConnectionError::Io(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    format!("Response stream ended {} bytes short of its Content-Length", short),
+                ))),
+                _ => Ok(written),
+            };
+        }
+
+        tokio::select! {
+            chunk = chunks.recv() => if let Some(chunk) = chunk {
+                write_frame(&mut pending, &chunk.bytes, &mut framing);
+                flushed.push(chunk.flushed);
+            },
+            e = stream.disconnected(), if !half_closed => match is_half_close(&e) {
+                true => half_closed = true,
+                false => return Err(ConnectionError::ResetByPeer(e)),
+            },
+        }
+    }
+}
+
+// A client that shut down its write half after sending the request still waits for the response.
+fn is_half_close(e: &std::io::Error) -> bool {
+    e.kind() == std::io::ErrorKind::UnexpectedEof
+}
+
+fn write_frame(output: &mut Vec<u8>, bytes: &[u8], framing: &mut Framing) {
+    let remaining = match framing {
+        Framing::Chunked => return write_chunk_frame(output, bytes),
+        Framing::Close => return output.extend_from_slice(bytes),
+        Framing::Sized(remaining) => remaining,
+    };
+
+    let count = (*remaining).min(bytes.len() as u64) as usize;
+    if count < bytes.len() {
+        log::warn!("Dropped {} response bytes beyond the Content-Length", bytes.len() - count);
+    }
+
+    output.extend_from_slice(&bytes[..count]);
+    *remaining -= count as u64;
+}
+
+fn write_chunk_frame(output: &mut Vec<u8>, bytes: &[u8]) {
+    output.extend_from_slice(format!("{:x}\r\n", bytes.len()).as_bytes());
+    output.extend_from_slice(bytes);
+    output.extend_from_slice(b"\r\n");
+}
+
+async fn write_all<T: Transport>(stream: &mut T, mut bytes: &[u8], mut throttle: Option<&mut TokenBucket>) -> Result<u64, ConnectionError> {
+    let mut written = 0;
+    while !bytes.is_empty() {
+        let chunk_size = match throttle.as_deref_mut() {
+            Some(throttle) => {
+                let chunk_size = bytes.len().min(IO_CHUNK_SIZE).min(throttle.capacity() as usize);
+                throttle.take(chunk_size as u64).await;
+                chunk_size
+            },
+            None => bytes.len(),
+        };
+
+        let mut chunk = &bytes[..chunk_size];
+        while !chunk.is_empty() {
+            match stream.write(chunk).await? {
+                0 => return Err(ConnectionError::ResetByPeer(std::io::ErrorKind::WriteZero.into())),
+                count => {
+                    chunk = &chunk[count..];
+                    written += count as u64;
+                },
+            }
+        }
+
+        bytes = &bytes[chunk_size..];
+    }
+
+    stream.flush().await?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{middleware::{Middleware, Next}, streaming::streaming_response};
+
+    use super::*;
+
+    async fn read_chunked(input: Vec<u8>) -> Result<Body, ConnectionError> {
+        let (mut client, mut server) = tokio::io::duplex(IO_CHUNK_SIZE);
+        let writer = tokio::spawn(async move {
+            // Errors just mean the server stopped reading early.
+            let _ = client.write_all(&input).await;
+            client
+        });
+
+        let memory = Arc::new(MemoryAccount::new("test", None));
+        let result = read_chunked_body(&mut server, &ListenerConfig::default(), &memory, &mut Vec::new(), None).await;
+        drop(server);
+        writer.await.unwrap();
+        result
+    }
+
+    async fn exchange(router: Router, request: &[u8]) -> String {
+        let (mut client, server) = tokio::io::duplex(IO_CHUNK_SIZE);
+        let context = ServerContext { router: Arc::new(router), ..ServerContext::default() };
+        let connection = tokio::spawn(handle_connection(server, UNKNOWN_PEER, ListenerConfig::default(), context));
+
+        client.write_all(request).await.unwrap();
+        let mut response = Vec::new();
+        client.read_to_end(&mut response).await.unwrap();
+        drop(client);
+        connection.await.unwrap().unwrap();
+        String::from_utf8(response).unwrap()
+    }
+
+    fn streaming_router() -> Router {
+        let mut router = Router::new();
+        router.get("/stream", |_| {
+            let (response, mut writer) = streaming_response(HttpStatusCode::OK);
+            tokio::spawn(async move {
+                writer.write("hello");
+                writer.finish().await.unwrap();
+            });
+
+            response
+        });
+
+        router
+    }
+
+    #[tokio::test]
+    async fn unsized_stream_is_chunked_for_http_1_1() {
+        let response = exchange(streaming_router(), b"GET /stream HTTP/1.1\r\nHost: a\r\nConnection: close\r\n\r\n").await;
+
+        assert!(response.contains("Transfer-Encoding: chunked\r\n"));
+        assert!(response.ends_with("\r\n\r\n5\r\nhello\r\n0\r\n\r\n"));
+    }
+
+    #[tokio::test]
+    async fn unsized_stream_is_close_delimited_for_http_1_0() {
+        let response = exchange(streaming_router(), b"GET /stream HTTP/1.0\r\nConnection: keep-alive\r\n\r\n").await;
+
+        assert!(!response.contains("Transfer-Encoding"));
+        assert!(response.contains("Connection: close\r\n"));
+        assert!(response.ends_with("\r\n\r\nhello"));
+    }
+
+    struct Delay(Duration);
+
+    impl Middleware for Delay {
+        async fn handle(&self, request: &mut HttpRequest, next: Next<'_>) -> HttpResponse {
+            tokio::time::sleep(self.0).await;
+            next.run(request).await
+        }
+    }
+
+    #[tokio::test]
+    async fn half_closed_client_still_gets_a_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = tokio::net::TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (server, addr) = listener.accept().await.unwrap();
+
+        let mut router = Router::new();
+        router.get("/slow", |_| HttpResponse::ok("done"));
+        router.middleware(Delay(Duration::from_millis(50)));
+        let context = ServerContext { router: Arc::new(router), ..ServerContext::default() };
+        let connection = tokio::spawn(handle_connection(server, addr, ListenerConfig::default(), context));
+
+        client.write_all(b"GET /slow HTTP/1.1\r\nHost: a\r\n\r\n").await.unwrap();
+        client.shutdown().await.unwrap();
+        let mut response = Vec::new();
+        client.read_to_end(&mut response).await.unwrap();
+        connection.await.unwrap().unwrap();
+
+        let response = String::from_utf8(response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Connection: close\r\n"));
+        assert!(response.ends_with("done"));
+    }
+
+    #[tokio::test]
+    async fn many_small_trailers_are_limited() {
+        let mut input = b"3\r\nabc\r\n0\r\n".to_vec();
+        input.extend(b"a\r\n".repeat(MAX_TRAILER_SIZE));
+        input.extend_from_slice(b"\r\n");
+
+        assert!(matches!(read_chunked(input).await, Err(ConnectionError::LimitExceeded(_))));
+    }
+
+    #[tokio::test]
+    async fn complete_long_trailer_is_limited() {
+        let mut input = b"0\r\nX-Long: ".to_vec();
+        input.extend(vec![b'a'; MAX_TRAILER_SIZE]);
+        input.extend_from_slice(b"\r\n\r\n");
+
+        assert!(matches!(read_chunked(input).await, Err(ConnectionError::LimitExceeded(_))));
+    }
+
+    #[tokio::test]
+    async fn trailers_within_the_limit_are_accepted() {
+        let body = read_chunked(b"3\r\nabc\r\n0\r\nX-Checksum: 1\r\n\r\n".to_vec()).await.unwrap();
+
+        assert_eq!(body.in_memory(), Some(&b"abc"[..]));
+    }
+}
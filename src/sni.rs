@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+use err_derive::Error;
+
+use crate::models::{Host, HostName};
+
+const CONTENT_TYPE_HANDSHAKE: u8 = 0x16;
+const HANDSHAKE_CLIENT_HELLO: u8 = 0x01;
+const EXTENSION_SERVER_NAME: u16 = 0x0000;
+const SERVER_NAME_HOST: u8 = 0x00;
+const RECORD_HEADER_LEN: usize = 5;
+
+#[derive(Debug, Error)]
+pub enum SniError {
+    #[error(display = "More data is needed to read the TLS ClientHello")]
+    Incomplete,
+    #[error(display = "The connection did not start with a TLS handshake")]
+    NotTls,
+    #[error(display = "Malformed TLS ClientHello: {}", _0)]
+    Malformed(&'static str),
+    #[error(display = "No backend is configured for server name '{}'", _0)]
+    NoRoute(String),
+}
+
+struct Reader<'a> {
+    input: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SniError> {
+        if self.input.len() < len {
+            return Err(SniError::Malformed("field extends past the end of its container"));
+        }
+
+        let (taken, rest) = self.input.split_at(len);
+        self.input = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, SniError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, SniError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u24(&mut self) -> Result<usize, SniError> {
+        let bytes = self.take(3)?;
+        Ok(u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]) as usize)
+    }
+
+    fn vec_u8(&mut self) -> Result<&'a [u8], SniError> {
+        let len = self.u8()? as usize;
+        self.take(len)
+    }
+
+    fn vec_u16(&mut self) -> Result<&'a [u8], SniError> {
+        let len = self.u16()? as usize;
+        self.take(len)
+    }
+}
+
+pub fn read_server_name(input: &[u8]) -> Result<Option<String>, SniError> {
+    if input.len() < RECORD_HEADER_LEN {
+        return Err(SniError::Incomplete);
+    }
+
+    if input[0] != CONTENT_TYPE_HANDSHAKE || input[1] != 0x03 {
+        return Err(SniError::NotTls);
+    }
+
+    let record_len = u16::from_be_bytes([input[3], input[4]]) as usize;
+    let record = input[RECORD_HEADER_LEN..]
+        .get(..record_len)
+        .ok_or(SniError::Incomplete)?;
+
+    let mut handshake = Reader { input: record };
+    if handshake.u8()? != HANDSHAKE_CLIENT_HELLO {
+        return Err(SniError::Malformed("first handshake message is not a ClientHello"));
+    }
+
+    let hello_len = handshake.u24()?;
+    if hello_len > handshake.input.len() {
+        return Err(SniError::Malformed("ClientHello spans several records"));
+    }
+
+    let mut hello = Reader { input: handshake.take(hello_len)? };
+    hello.take(2 + 32)?;
+    hello.vec_u8()?;
+    hello.vec_u16()?;
+    hello.vec_u8()?;
+
+    if hello.input.is_empty() {
+        return Ok(None);
+    }
+
+    let mut extensions = Reader { input: hello.vec_u16()? };
+    while !extensions.input.is_empty() {
+        let extension_type = extensions.u16()?;
+        let data = extensions.vec_u16()?;
+        if extension_type != EXTENSION_SERVER_NAME {
+            continue;
+        }
+
+        let mut names = Reader { input: Reader { input: data }.vec_u16()? };
+        while !names.input.is_empty() {
+            let name_type = names.u8()?;
+            let name = names.vec_u16()?;
+            if name_type == SERVER_NAME_HOST {
+                let name = std::str::from_utf8(name).map_err(|_| SniError::Malformed("server name is not valid UTF-8"))?;
+                return Ok(Some(name.to_string()));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SniRouter {
+    routes: HashMap<String, String>,
+    fallback: Option<String>,
+}
+
+impl SniRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn route(mut self, server_name: &str, backend: impl std::fmt::Display) -> Result<Self, String> {
+        let name = normalize_server_name(server_name)
+            .ok_or_else(|| format!("'{}' is not a valid server name", server_name))?;
+
+        self.routes.insert(name, backend.to_string());
+        Ok(self)
+    }
+
+    pub fn fallback(mut self, backend: impl std::fmt::Display) -> Self {
+        self.fallback = Some(backend.to_string());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty() && self.fallback.is_none()
+    }
+
+    pub fn backend(&self, server_name: Option<&str>) -> Option<&str> {
+        server_name
+            .and_then(normalize_server_name)
+            .and_then(|name| self.routes.get(&name))
+            .or(self.fallback.as_ref())
+            .map(String::as_str)
+    }
+}
+
+fn normalize_server_name(name: &str) -> Option<String> {
+    match name.parse::<Host>().ok()? {
+        host if host.port().is_some() => None,
+        host => match host.name() {
+            HostName::Domain(domain) => Some(domain.clone()),
+            HostName::Ipv4(_) | HostName::Ipv6(_) => Some(host.to_string()),
+        },
+    }
+}
@@ -0,0 +1,277 @@
+use std::{fmt::Display, fs::Metadata, io::SeekFrom, path::{Component, Path, PathBuf}, time::UNIX_EPOCH};
+
+use tokio::{fs::File, io::{AsyncReadExt, AsyncSeekExt}};
+
+use crate::{
+    conditional::{self, EntityTag, Validators},
+    middleware::{Middleware, Next},
+    models::{HttpMethod, HttpRequest, HttpResponse, HttpStatusCode},
+    router::Router,
+    streaming::sized_streaming_response,
+};
+
+const DEFAULT_PATH_PARAM: &str = "path";
+const READ_CHUNK_SIZE: usize = 16 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct StaticFiles {
+    root: PathBuf,
+    param: String,
+}
+
+impl StaticFiles {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        Self { root: root.canonicalize().unwrap_or(root), param: DEFAULT_PATH_PARAM.to_string() }
+    }
+
+    pub fn with_param(mut self, param: impl Into<String>) -> Self {
+        self.param = param.into();
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub async fn serve(&self, request: &HttpRequest) -> HttpResponse {
+        let opened = match request.path_param(&self.param) {
+            Some(path) => self.open(path).await,
+            None => None,
+        };
+
+        let Some((path, mut file, metadata)) = opened else {
+            return HttpResponse::not_found();
+        };
+
+        let validators = validators(&metadata);
+        if let Some(response) = conditional::evaluate(request, &validators).response(&validators) {
+            return response;
+        }
+
+        let len = metadata.len();
+        let range = match conditional::range_applies(request, &validators) {
+            true => byte_range(request.headers().get("Range").unwrap_or_default(), len),
+            false => ByteRange::Full,
+        };
+
+        let (status, start, count) = match range {
+            ByteRange::Full => (HttpStatusCode::OK, 0, len),
+            ByteRange::Partial(start, end) => (HttpStatusCode::PartialContent, start, end - start + 1),
+            ByteRange::Unsatisfiable => {
+                return HttpResponse::builder()
+                    .status(HttpStatusCode::RangeNotSatisfiable)
+                    .header("Content-Range", format!("bytes */{}", len))
+                    .body(HttpStatusCode::RangeNotSatisfiable.get_readable_name());
+            },
+        };
+
+        let (mut response, mut writer) = sized_streaming_response(status, count);
+        response.headers_mut().insert("Content-Type", content_type(&path));
+        response.headers_mut().insert("Accept-Ranges", "bytes");
+        if status == HttpStatusCode::PartialContent {
+            response.headers_mut().insert("Content-Range", format!("bytes {}-{}/{}", start, start + count - 1, len));
+        }
+
+        validators.apply(&mut response);
+
+        tokio::spawn(async move {
+            if writer.started().await.is_err() {
+                return;
+            }
+
+            if let Err(e) = file.seek(SeekFrom::Start(start)).await {
+                log::warn!("Failed to seek in '{}': {}", path.display(), e);
+                return;
+            }
+
+            let mut file = file.take(count);
+            let mut buffer = vec![0_u8; READ_CHUNK_SIZE];
+            loop {
+                match file.read(&mut buffer).await {
+                    Ok(0) => break,
+                    Ok(count) => {
+                        writer.write(&buffer[..count]);
+                        if writer.flush().await.is_err() {
+                            break;
+                        }
+                    },
+                    Err(e) => {
+                        log::warn!("Failed to read '{}': {}", path.display(), e);
+                        break;
+                    },
+                }
+            }
+        });
+
+        response
+    }
+
+    async fn open(&self, path: &str) -> Option<(PathBuf, File, Metadata)> {
+        let mut resolved = self.root.clone();
+        for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+            let mut components = Path::new(segment).components();
+            match (components.next(), components.next()) {
+                (Some(Component::Normal(_)), None) if !segment.contains('\\') => resolved.push(segment),
+                _ => return None,
+            }
+        }
+
+        let resolved = tokio::fs::canonicalize(&resolved).await.ok()?;
+        if !resolved.starts_with(&self.root) {
+            return None;
+        }
+
+        let file = File::open(&resolved).await.ok()?;
+        let metadata = file.metadata().await.ok()?;
+        metadata.is_file().then_some((resolved, file, metadata))
+    }
+}
+
+// Router endpoints are synchronous, so the files are served from route middleware that never calls the endpoint.
+impl Middleware for StaticFiles {
+    async fn handle(&self, request: &mut HttpRequest, _next: Next<'_>) -> HttpResponse {
+        self.serve(request).await
+    }
+}
+
+pub fn static_files(router: &mut Router, path: impl Display, root: impl Into<PathBuf>) -> &mut Router {
+    let path = path.to_string();
+    router
+        .get(&path, |_| HttpResponse::not_found())
+        .route_middleware(HttpMethod::GET, &path, StaticFiles::new(root))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ByteRange {
+    Full,
+    Partial(u64, u64),
+    Unsatisfiable,
+}
+
+// Only a single byte range is served; other range forms are ignored and get the full file.
+fn byte_range(value: &str, len: u64) -> ByteRange {
+    let Some((first, last)) = value.trim().strip_prefix("bytes=").filter(|spec| !spec.contains(',')).and_then(|spec| spec.split_once('-')) else {
+        return ByteRange::Full;
+    };
+
+    let position = |value: &str| match !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) {
+        true => value.parse::<u64>().ok(),
+        false => None,
+    };
+
+    let (start, end) = match (first.trim(), last.trim()) {
+        ("", suffix) => match position(suffix) {
+            Some(0) => return ByteRange::Unsatisfiable,
+            Some(suffix) => (len.saturating_sub(suffix), u64::MAX),
+            None => return ByteRange::Full,
+        },
+        (start, "") => match position(start) {
+            Some(start) => (start, u64::MAX),
+            None => return ByteRange::Full,
+        },
+        (start, end) => match (position(start), position(end)) {
+            (Some(start), Some(end)) if start <= end => (start, end),
+            _ => return ByteRange::Full,
+        },
+    };
+
+    match start < len {
+        true => ByteRange::Partial(start, end.min(len - 1)),
+        false => ByteRange::Unsatisfiable,
+    }
+}
+
+fn validators(metadata: &Metadata) -> Validators {
+    let modified = metadata.modified().ok();
+    let etag = modified
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|elapsed| EntityTag::strong(format!("{:x}-{:x}", metadata.len(), elapsed.as_nanos())));
+
+    Validators::new(etag, modified)
+}
+
+fn content_type(path: &Path) -> &'static str {
+    let extension = path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" => "application/json",
+        "txt" => "text/plain; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn get(router: &Router, target: &str) -> HttpResponse {
+        let mut request = HttpRequest::new(format!("GET {} HTTP/1.1\r\nHost: a\r\n\r\n", target).as_bytes()).unwrap();
+        router.handle(&mut request).await
+    }
+
+    #[tokio::test]
+    async fn serves_files_under_the_root_only() {
+        let root = std::env::temp_dir().join(format!("static-files-test-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("public")).unwrap();
+        std::fs::write(root.join("public/a.txt"), "hello").unwrap();
+        std::fs::write(root.join("secret.txt"), "hidden").unwrap();
+
+        let mut router = Router::new();
+        static_files(&mut router, "/static/*path", root.join("public"));
+
+        let found = get(&router, "/static/a.txt").await;
+        let escaped = get(&router, "/static/../secret.txt").await;
+        let missing = get(&router, "/static/b.txt").await;
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(found.status(), HttpStatusCode::OK);
+        assert_eq!(found.headers().get("Content-Length"), Some("5"));
+        assert_eq!(found.headers().get("Content-Type"), Some("text/plain; charset=utf-8"));
+        assert_eq!(escaped.status(), HttpStatusCode::NotFound);
+        assert_eq!(missing.status(), HttpStatusCode::NotFound);
+    }
+
+    #[test]
+    fn byte_ranges_are_clamped_to_the_file() {
+        assert_eq!(byte_range("bytes=0-9", 100), ByteRange::Partial(0, 9));
+        assert_eq!(byte_range("bytes=90-200", 100), ByteRange::Partial(90, 99));
+        assert_eq!(byte_range("bytes=10-", 100), ByteRange::Partial(10, 99));
+        assert_eq!(byte_range("bytes=-10", 100), ByteRange::Partial(90, 99));
+        assert_eq!(byte_range("bytes=-500", 100), ByteRange::Partial(0, 99));
+    }
+
+    #[test]
+    fn unsatisfiable_byte_ranges() {
+        assert_eq!(byte_range("bytes=100-", 100), ByteRange::Unsatisfiable);
+        assert_eq!(byte_range("bytes=-0", 100), ByteRange::Unsatisfiable);
+        assert_eq!(byte_range("bytes=0-", 0), ByteRange::Unsatisfiable);
+    }
+
+    #[test]
+    fn unsupported_or_invalid_ranges_serve_the_full_file() {
+        assert_eq!(byte_range("bytes=0-1, 5-6", 100), ByteRange::Full);
+        assert_eq!(byte_range("items=0-1", 100), ByteRange::Full);
+        assert_eq!(byte_range("bytes=5-1", 100), ByteRange::Full);
+        assert_eq!(byte_range("bytes=+1-2", 100), ByteRange::Full);
+        assert_eq!(byte_range("bytes=-", 100), ByteRange::Full);
+    }
+}
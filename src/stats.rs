@@ -0,0 +1,145 @@
+use std::sync::{atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering}, Arc};
+
+use crate::errors::ConnectionErrorKind;
+
+#[derive(Debug, Default)]
+pub struct ListenerStats {
+    active_requests: AtomicUsize,
+    total_requests: AtomicU64,
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+    slow_requests: AtomicU64,
+    errors: [AtomicU64; ConnectionErrorKind::ALL.len()],
+    draining: AtomicBool,
+}
+
+impl ListenerStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn track_request(self: &Arc<Self>) -> RequestGuard {
+        self.active_requests.fetch_add(1, Ordering::SeqCst);
+        self.total_requests.fetch_add(1, Ordering::Relaxed);
+        RequestGuard { stats: self.clone() }
+    }
+
+    pub fn active_requests(&self) -> usize {
+        self.active_requests.load(Ordering::SeqCst)
+    }
+
+    pub fn total_requests(&self) -> u64 {
+        self.total_requests.load(Ordering::Relaxed)
+    }
+
+    pub fn record_transfer(&self, bytes_read: u64, bytes_written: u64) {
+        self.bytes_read.fetch_add(bytes_read, Ordering::Relaxed);
+        self.bytes_written.fetch_add(bytes_written, Ordering::Relaxed);
+    }
+
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read.load(Ordering::Relaxed)
+    }
+
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written.load(Ordering::Relaxed)
+    }
+
+    pub fn record_slow_request(&self) {
+        self.slow_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn slow_requests(&self) -> u64 {
+        self.slow_requests.load(Ordering::Relaxed)
+    }
+
+    pub fn record_error(&self, kind: ConnectionErrorKind) {
+        self.errors[kind as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn errors(&self, kind: ConnectionErrorKind) -> u64 {
+        self.errors[kind as usize].load(Ordering::Relaxed)
+    }
+
+    pub fn start_draining(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    pub fn is_ready(&self) -> bool {
+        !self.is_draining()
+    }
+}
+
+impl std::fmt::Display for ListenerStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "active requests: {}, total requests: {}, slow requests: {}, bytes read: {}, bytes written: {}, draining: {}",
+            self.active_requests(),
+            self.total_requests(),
+            self.slow_requests(),
+            self.bytes_read(),
+            self.bytes_written(),
+            self.is_draining()
+        )?;
+
+        for kind in ConnectionErrorKind::ALL {
+            write!(f, ", {} errors: {}", kind, self.errors(kind))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct RequestGuard {
+    stats: Arc<ListenerStats>,
+}
+
+impl Drop for RequestGuard {
+    fn drop(&mut self) {
+        self.stats.active_requests.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(feature = "runtime-metrics")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeStats {
+    pub workers: usize,
+    pub alive_tasks: usize,
+    pub global_queue_depth: usize,
+    pub worker_busy: Vec<std::time::Duration>,
+    pub worker_parks: Vec<u64>,
+}
+
+#[cfg(feature = "runtime-metrics")]
+impl RuntimeStats {
+    pub fn capture() -> Option<Self> {
+        let metrics = tokio::runtime::Handle::try_current().ok()?.metrics();
+        let workers = metrics.num_workers();
+
+        Some(Self {
+            workers,
+            alive_tasks: metrics.num_alive_tasks(),
+            global_queue_depth: metrics.global_queue_depth(),
+            worker_busy: (0..workers).map(|worker| metrics.worker_total_busy_duration(worker)).collect(),
+            worker_parks: (0..workers).map(|worker| metrics.worker_park_count(worker)).collect(),
+        })
+    }
+}
+
+#[cfg(feature = "runtime-metrics")]
+impl std::fmt::Display for RuntimeStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "workers: {}, alive tasks: {}, global queue depth: {}", self.workers, self.alive_tasks, self.global_queue_depth)?;
+        for (worker, (busy, parks)) in self.worker_busy.iter().zip(&self.worker_parks).enumerate() {
+            write!(f, ", worker {}: busy {:.3}ms parks {}", worker, busy.as_secs_f64() * 1000.0, parks)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use std::{collections::HashMap, net::{Ipv4Addr, Ipv6Addr, SocketAddr}, sync::Mutex, time::Duration};
+
+use tokio::net::UdpSocket;
+
+use crate::{errors::ConnectionErrorKind, server::ServerContext};
+
+pub const DEFAULT_STATSD_PREFIX: &str = "http_server";
+pub const DEFAULT_STATSD_INTERVAL: Duration = Duration::from_secs(10);
+const MAX_DATAGRAM_SIZE: usize = 1432;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatsdConfig {
+    pub addr: String,
+    pub prefix: String,
+    pub interval: Duration,
+}
+
+impl StatsdConfig {
+    pub fn new(addr: impl Into<String>) -> Self {
+        Self { addr: addr.into(), prefix: DEFAULT_STATSD_PREFIX.to_string(), interval: DEFAULT_STATSD_INTERVAL }
+    }
+}
+
+#[derive(Debug)]
+pub struct StatsdExporter {
+    config: StatsdConfig,
+    socket: UdpSocket,
+    counters: Mutex<HashMap<String, u64>>,
+}
+
+impl StatsdExporter {
+    pub async fn connect(config: StatsdConfig) -> std::io::Result<Self> {
+        let target = tokio::net::lookup_host(&config.addr)
+            .await?
+            .next()
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, format!("'{}' did not resolve to an address", config.addr)))?;
+
+        let local = match target {
+            SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+            SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+        };
+
+        let socket = UdpSocket::bind(local).await?;
+        socket.connect(target).await?;
+
+        Ok(Self { config, socket, counters: Mutex::default() })
+    }
+
+    pub fn config(&self) -> &StatsdConfig {
+        &self.config
+    }
+
+    pub async fn push(&self, context: &ServerContext) -> std::io::Result<()> {
+        let lines = self.collect(context);
+        let mut datagram = String::new();
+        for line in lines {
+            if !datagram.is_empty() && datagram.len() + line.len() + 1 > MAX_DATAGRAM_SIZE {
+                self.socket.send(datagram.as_bytes()).await?;
+                datagram.clear();
+            }
+
+            if !datagram.is_empty() {
+                datagram.push('\n');
+            }
+
+            datagram.push_str(&line);
+        }
+
+        if !datagram.is_empty() {
+            self.socket.send(datagram.as_bytes()).await?;
+        }
+
+        Ok(())
+    }
+
+    fn collect(&self, context: &ServerContext) -> Vec<String> {
+        let stats = &context.stats;
+        let mut counters = vec![
+            ("requests".to_string(), stats.total_requests()),
+            ("slow_requests".to_string(), stats.slow_requests()),
+            ("bytes_read".to_string(), stats.bytes_read()),
+            ("bytes_written".to_string(), stats.bytes_written()),
+            ("admission.shed".to_string(), context.admission.shed()),
+            ("body_memory.shed".to_string(), context.body_memory.shed()),
+        ];
+        counters.extend(ConnectionErrorKind::ALL.map(|kind| (format!("errors.{}", kind.name()), stats.errors(kind))));
+        #[cfg(feature = "geoip")]
+        counters.extend(context.geoip.countries().into_iter().map(|(country, count)| (format!("requests.country.{}", country), count)));
+
+        let gauges = [
+            ("active_requests", stats.active_requests() as u64),
+            ("draining", stats.is_draining() as u64),
+            ("admission.active", context.admission.active() as u64),
+            ("admission.queued", context.admission.queued() as u64),
+            ("body_memory.used", context.body_memory.used() as u64),
+        ];
+
+        // Statsd counters are increments, so only send what changed since the last push.
+        let mut previous = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        let mut lines = counters.into_iter()
+            .map(|(name, value)| {
+                let delta = value.saturating_sub(previous.insert(name.clone(), value).unwrap_or_default());
+                format!("{}.{}:{}|c", self.config.prefix, name, delta)
+            })
+            .collect::<Vec<_>>();
+
+        lines.extend(gauges.iter().map(|(name, value)| format!("{}.{}:{}|g", self.config.prefix, name, value)));
+        lines
+    }
+}
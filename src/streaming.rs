@@ -0,0 +1,152 @@
+use std::{io, sync::{Arc, Mutex}};
+
+use tokio::sync::{mpsc::{self, error::TrySendError}, oneshot::{self, error::TryRecvError}};
+
+use crate::models::{HttpResponse, HttpStatusCode};
+
+#[derive(Debug)]
+pub(crate) struct Chunk {
+    pub(crate) bytes: Vec<u8>,
+    pub(crate) flushed: oneshot::Sender<()>,
+}
+
+#[derive(Debug)]
+struct PendingStream {
+    receiver: mpsc::Receiver<Chunk>,
+    started: oneshot::Sender<()>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ResponseStream {
+    pending: Arc<Mutex<Option<PendingStream>>>,
+    length: Option<u64>,
+}
+
+impl ResponseStream {
+    pub fn length(&self) -> Option<u64> {
+        self.length
+    }
+
+    pub(crate) fn take(&self) -> Option<mpsc::Receiver<Chunk>> {
+        let pending = self.pending.lock().expect("Response stream lock poisoned").take()?;
+        let _ = pending.started.send(());
+        Some(pending.receiver)
+    }
+}
+
+#[derive(Debug)]
+pub struct ResponseWriter {
+    buffer: Vec<u8>,
+    sender: mpsc::Sender<Chunk>,
+    started: Option<oneshot::Receiver<()>>,
+}
+
+impl ResponseWriter {
+    pub fn write(&mut self, bytes: impl AsRef<[u8]>) {
+        self.buffer.extend_from_slice(bytes.as_ref());
+    }
+
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    // The server only starts writing once the handler has returned the response, so until then
+    // flush keeps the bytes buffered rather than waiting for a write that cannot happen yet.
+    pub async fn flush(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() || !self.is_started()? {
+            return Ok(());
+        }
+
+        let (flushed, done) = oneshot::channel();
+        let chunk = Chunk { bytes: std::mem::take(&mut self.buffer), flushed };
+        self.sender.send(chunk).await.map_err(|_| closed())?;
+        done.await.map_err(|_| closed())
+    }
+
+    pub async fn finish(mut self) -> io::Result<()> {
+        if self.is_started()? {
+            return self.flush().await;
+        }
+
+        // Nothing is sent before the response is written, so the channel has room for the final chunk.
+        let (flushed, _) = oneshot::channel();
+        let chunk = Chunk { bytes: std::mem::take(&mut self.buffer), flushed };
+        self.sender.send(chunk).await.map_err(|_| closed())
+    }
+
+    pub async fn started(&mut self) -> io::Result<()> {
+        if let Some(started) = &mut self.started {
+            started.await.map_err(|_| closed())?;
+            self.started = None;
+        }
+
+        Ok(())
+    }
+
+    pub fn is_started(&mut self) -> io::Result<bool> {
+        let Some(started) = &mut self.started else {
+            return Ok(true);
+        };
+
+        match started.try_recv() {
+            Ok(()) => {
+                self.started = None;
+                Ok(true)
+            },
+            Err(TryRecvError::Empty) => Ok(false),
+            Err(TryRecvError::Closed) => Err(closed()),
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.sender.is_closed()
+    }
+
+    pub async fn closed(&self) {
+        self.sender.closed().await
+    }
+}
+
+impl Drop for ResponseWriter {
+    fn drop(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let (flushed, _) = oneshot::channel();
+        let chunk = Chunk { bytes: std::mem::take(&mut self.buffer), flushed };
+        if let Err(TrySendError::Full(chunk)) = self.sender.try_send(chunk) {
+            // A cancelled flush can leave its chunk in the channel, so deliver the rest once it drains.
+            let sender = self.sender.clone();
+            match tokio::runtime::Handle::try_current() {
+                Ok(runtime) => { runtime.spawn(async move { let _ = sender.send(chunk).await; }); },
+                Err(_) => log::warn!("Dropped {} buffered response bytes outside of a runtime", chunk.bytes.len()),
+            }
+        }
+    }
+}
+
+pub fn streaming_response(status: HttpStatusCode) -> (HttpResponse, ResponseWriter) {
+    stream_response(status, None)
+}
+
+pub fn sized_streaming_response(status: HttpStatusCode, length: u64) -> (HttpResponse, ResponseWriter) {
+    stream_response(status, Some(length))
+}
+
+fn stream_response(status: HttpStatusCode, length: Option<u64>) -> (HttpResponse, ResponseWriter) {
+    let (sender, receiver) = mpsc::channel(1);
+    let (started, started_receiver) = oneshot::channel();
+    let mut response = HttpResponse::builder().status(status).build();
+    match length {
+        Some(length) => response.headers_mut().insert("Content-Length", length),
+        None => response.headers_mut().insert("Transfer-Encoding", "chunked"),
+    };
+
+    response.extensions_mut().insert(ResponseStream { pending: Arc::new(Mutex::new(Some(PendingStream { receiver, started }))), length });
+    (response, ResponseWriter { buffer: Vec::new(), sender, started: Some(started_receiver) })
+}
+
+fn closed() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "The response stream was closed")
+}
@@ -0,0 +1,56 @@
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    rate: u64,
+    capacity: u64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(rate: u64) -> Self {
+        Self::with_capacity(rate, rate)
+    }
+
+    pub fn with_capacity(rate: u64, capacity: u64) -> Self {
+        let rate = rate.max(1);
+        let capacity = capacity.max(1);
+
+        Self { rate, capacity, tokens: capacity as f64, last_refill: Instant::now() }
+    }
+
+    pub fn rate(&self) -> u64 {
+        self.rate
+    }
+
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    pub fn try_take(&mut self, amount: u64) -> bool {
+        self.refill();
+        if self.tokens >= amount as f64 {
+            self.tokens -= amount as f64;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub async fn take(&mut self, amount: u64) {
+        self.refill();
+        self.tokens -= amount as f64;
+
+        if self.tokens < 0.0 {
+            tokio::time::sleep(Duration::from_secs_f64(-self.tokens / self.rate as f64)).await;
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate as f64).min(self.capacity as f64);
+        self.last_refill = now;
+    }
+}
@@ -0,0 +1,189 @@
+use std::{fmt::{Display, Write}, str::FromStr, sync::RwLock};
+
+use crate::{models::{Body, HttpRequest, HttpResponse}, redact::Redactor};
+
+const HEXDUMP_WIDTH: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TraceMode {
+    Off,
+    Headers,
+    #[default]
+    Full,
+}
+
+impl FromStr for TraceMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "off" => Ok(Self::Off),
+            "headers" => Ok(Self::Headers),
+            "full" => Ok(Self::Full),
+            _ => Err(format!("'{}' is not a valid trace mode", s)),
+        }
+    }
+}
+
+impl Display for TraceMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Off => "off",
+            Self::Headers => "headers",
+            Self::Full => "full",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceConfig {
+    pub mode: TraceMode,
+    pub body_limit: usize,
+}
+
+impl Default for TraceConfig {
+    fn default() -> Self {
+        Self { mode: TraceMode::default(), body_limit: 1024 }
+    }
+}
+
+impl Display for TraceConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "mode: {}, body limit: {} bytes", self.mode, self.body_limit)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RequestTracer {
+    config: RwLock<TraceConfig>,
+}
+
+impl RequestTracer {
+    pub fn new(config: TraceConfig) -> Self {
+        Self { config: RwLock::new(config) }
+    }
+
+    pub fn config(&self) -> TraceConfig {
+        *self.config.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn update(&self, f: impl FnOnce(&mut TraceConfig)) {
+        let mut config = self.config.write().unwrap_or_else(|e| e.into_inner());
+        f(&mut config);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config().mode != TraceMode::Off
+    }
+
+    pub fn trace(&self, request: &HttpRequest, redactor: &Redactor) -> Option<String> {
+        let config = self.config();
+        if config.mode == TraceMode::Off {
+            return None;
+        }
+
+        Some(format_request(request, redactor, config.mode == TraceMode::Full, config.body_limit))
+    }
+
+    pub fn trace_raw(&self, bytes: &[u8], redactor: &Redactor) -> Option<String> {
+        let config = self.config();
+        if config.mode == TraceMode::Off || std::str::from_utf8(bytes).is_ok() {
+            return None;
+        }
+
+        let mut output = format!("Received {} bytes that are not a valid request\n", bytes.len());
+        let bytes = redactor.raw_head(bytes);
+        let shown = &bytes[..bytes.len().min(config.body_limit)];
+        write_hexdump(&mut output, shown);
+        if shown.len() < bytes.len() {
+            let _ = writeln!(output, "... {} more bytes", bytes.len() - shown.len());
+        }
+
+        Some(output)
+    }
+}
+
+pub fn format_request(request: &HttpRequest, redactor: &Redactor, with_body: bool, body_limit: usize) -> String {
+    let mut output = format!("{} {} {}\n", request.method(), redactor.redact_text(&request.route().to_string()), request.version());
+    for (key, val) in request.headers().iter() {
+        let _ = writeln!(output, "{}: {}", key, redactor.header_value(key, val));
+    }
+
+    if with_body && !request.body().is_empty() {
+        output.push('\n');
+        write_body(&mut output, request.body(), body_limit, redactor);
+    }
+
+    output
+}
+
+pub fn format_response(response: &HttpResponse, redactor: &Redactor, with_body: bool, body_limit: usize) -> String {
+    let mut output = format!("{} {}\n", response.version(), response.status());
+    for (key, val) in response.headers().iter() {
+        let _ = writeln!(output, "{}: {}", key, redactor.header_value(key, val));
+    }
+
+    if with_body && !response.body().is_empty() {
+        output.push('\n');
+        write_bytes(&mut output, response.body(), body_limit, redactor);
+    }
+
+    output
+}
+
+fn write_body(output: &mut String, body: &Body, limit: usize, redactor: &Redactor) {
+    match body.in_memory() {
+        Some(bytes) => write_bytes(output, bytes, limit, redactor),
+        None => { let _ = writeln!(output, "<{} bytes on disk>", body.len()); },
+    }
+}
+
+fn write_bytes(output: &mut String, bytes: &[u8], limit: usize, redactor: &Redactor) {
+    let shown = &bytes[..bytes.len().min(limit)];
+    match as_text(shown) {
+        Some(text) => {
+            output.push_str(&redactor.redact_text(text));
+            if !text.ends_with('\n') {
+                output.push('\n');
+            }
+        },
+        None => write_hexdump(output, shown),
+    }
+
+    if shown.len() < bytes.len() {
+        let _ = writeln!(output, "... {} more bytes", bytes.len() - shown.len());
+    }
+}
+
+fn as_text(bytes: &[u8]) -> Option<&str> {
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&bytes[..e.valid_up_to()]).ok()?,
+        Err(_) => return None,
+    };
+
+    match text.chars().all(|c| !c.is_control() || matches!(c, '\r' | '\n' | '\t')) {
+        true => Some(text),
+        false => None,
+    }
+}
+
+fn write_hexdump(output: &mut String, bytes: &[u8]) {
+    for (index, line) in bytes.chunks(HEXDUMP_WIDTH).enumerate() {
+        let _ = write!(output, "{:08x} ", index * HEXDUMP_WIDTH);
+        for column in 0..HEXDUMP_WIDTH {
+            match line.get(column) {
+                Some(b) => { let _ = write!(output, " {:02x}", b); },
+                None => output.push_str("   "),
+            }
+        }
+
+        let printable: String = line.iter()
+            .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+            .collect();
+
+        let _ = writeln!(output, "  |{}|", printable);
+    }
+}
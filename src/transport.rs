@@ -0,0 +1,49 @@
+use std::{future::Future, io, net::SocketAddr};
+
+use tokio::{io::{AsyncRead, AsyncWrite, DuplexStream}, net::TcpStream};
+
+pub trait Transport: AsyncRead + AsyncWrite + Unpin + Send + 'static {
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    fn reset(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn disconnected(&self) -> impl Future<Output = io::Error> + Send + '_ {
+        std::future::pending()
+    }
+}
+
+impl Transport for TcpStream {
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        TcpStream::peer_addr(self)
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        TcpStream::local_addr(self)
+    }
+
+    fn reset(&mut self) -> io::Result<()> {
+        self.set_zero_linger()
+    }
+
+    async fn disconnected(&self) -> io::Error {
+        match self.peek(&mut [0_u8; 1]).await {
+            // EOF may only be a half-close, so the server keeps the connection long enough to respond.
+            Ok(0) => io::ErrorKind::UnexpectedEof.into(),
+            Ok(_) => std::future::pending().await,
+            Err(e) => e,
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Transport for tokio::net::UnixStream {}
+
+impl Transport for DuplexStream {}